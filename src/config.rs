@@ -15,6 +15,7 @@ use starknet_api::{contract_address, patricia_key};
 use starknet_crypto::FieldElement;
 
 use crate::error::SnOsError;
+use crate::utils::ChainIdNum;
 
 const DEFAULT_CONFIG_PATH: &str = "cairo-lang/src/starkware/starknet/definitions/general_config.yml";
 
@@ -29,7 +30,24 @@ pub const DEFAULT_INNER_TREE_HEIGHT: u64 = 64;
 pub const DEFAULT_FEE_TOKEN_ADDR: &str = "482bc27fc5627bf974a72b65c43aa8a0464a70aab91ad8379b56a4f17a84c3";
 pub const SEQUENCER_ADDR_0_12_2: &str = "6c95526293b61fa708c6cba66fd015afee89309666246952456ab970e9650aa";
 
-use crate::utils::ChainIdNum;
+/// Builtins declared by the Starknet OS program, in declaration order.
+pub const OS_BUILTINS: [&str; 8] =
+    ["output", "pedersen", "range_check", "ecdsa", "bitwise", "ec_op", "keccak", "poseidon"];
+
+/// Returns the builtins available in a CairoVM layout, `None` if the layout is unknown.
+pub fn layout_builtins(layout: &str) -> Option<&'static [&'static str]> {
+    match layout {
+        "plain" => Some(&[]),
+        "small" | "dex" => Some(&["output", "pedersen", "range_check", "ecdsa"]),
+        "recursive" => Some(&["output", "pedersen", "range_check", "bitwise"]),
+        "starknet" => Some(&["output", "pedersen", "range_check", "ecdsa", "bitwise", "ec_op", "poseidon"]),
+        "recursive_large_output" => Some(&["output", "pedersen", "range_check", "bitwise", "poseidon"]),
+        "all_solidity" => Some(&["output", "pedersen", "range_check", "ecdsa", "bitwise", "ec_op"]),
+        "starknet_with_keccak" | "all_cairo" | "dynamic" => Some(&OS_BUILTINS),
+        _ => None,
+    }
+}
+
 #[serde_as]
 #[derive(Debug, Serialize, Clone, Deserialize)]
 pub struct StarknetOsConfig {
@@ -169,4 +187,11 @@ mod tests {
         assert_eq!(conf.sequencer_address, ctx.sequencer_address);
         assert_eq!(conf.cairo_resource_fee_weights, ctx.vm_resource_fee_cost);
     }
+
+    #[test]
+    fn os_layout_builtins() {
+        assert_eq!(Some(&OS_BUILTINS[..]), layout_builtins(DEFAULT_LAYOUT));
+        assert!(!layout_builtins("small").unwrap().contains(&"keccak"));
+        assert!(layout_builtins("not_a_layout").is_none());
+    }
 }
@@ -12,17 +12,25 @@ pub enum SnOsError {
     #[error("PIE Encode Error: {0}")]
     PieEncoding(String),
     #[error("SHARP Request Error: {0}")]
-    SharpRequest(String),
+    Sharp(#[from] SharpError),
     #[error("Starknet Os Runner Error: {0}")]
     Runner(CairoRunError),
+    #[error("Starknet Os Runner Config Error: {0}")]
+    RunnerConfig(String),
+    #[error("Block Sequence Error: {0}")]
+    BlockSequence(String),
+    #[error("State Root Mismatch: block {0} prev_state_root {1} does not match parent new_state_root {2}")]
+    StateRootMismatch(u64, Felt252, Felt252),
     #[error("SnOs Output Error: {0}")]
-    Output(String),
+    Output(#[from] OutputError),
     #[error(transparent)]
     IO(#[from] std::io::Error),
     #[error(transparent)]
     SerdeJson(#[from] serde_json::Error),
     #[error("SnOs Deprecated Syscall Error: {0}")]
     InvalidDeprecatedSyscallSelector(Felt252),
+    #[error(transparent)]
+    OsInput(#[from] OsInputError),
 }
 
 #[derive(thiserror::Error, Clone, Debug)]
@@ -31,6 +39,81 @@ pub enum CommitmentInfoError {
     InconsistentTreeHeights(usize, usize),
     #[error("Inconsistent tree roots, actual : {0} , expected : {1}.")]
     InconsistentTreeRoots(Felt252, Felt252),
+    #[error("Missing commitment fact for root : {0}.")]
+    MissingCommitmentFact(Felt252),
+    #[error("Commitment fact preimage does not hash to : {0}.")]
+    InvalidCommitmentFact(Felt252),
+}
+
+#[derive(thiserror::Error, Clone, Debug)]
+pub enum OsInputError {
+    #[error(transparent)]
+    CommitmentInfo(#[from] CommitmentInfoError),
+    #[error("Contract {0} has unknown class hash : {1}.")]
+    UnknownClassHash(Felt252, Felt252),
+    #[error("Transaction {0} is invalid : {1}.")]
+    InvalidTransaction(Felt252, String),
+}
+
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+pub enum OutputError {
+    #[error("Unexpected end of output at offset {0}.")]
+    UnexpectedEnd(usize),
+    #[error("Segment of {1} felts at offset {0} exceeds the output.")]
+    SegmentOutOfBounds(usize, usize),
+    #[error("Invalid length at offset {0} : {1}.")]
+    InvalidLength(usize, Felt252),
+    #[error("Invalid class update flag at offset {0} : {1}.")]
+    InvalidClassUpdateFlag(usize, Felt252),
+    #[error("Unexpected trailing data at offset {0}, {1} felts left.")]
+    TrailingData(usize, usize),
+    #[error("Output cell at offset {0} is not an integer.")]
+    NonIntegerCell(usize),
+    #[error("The PIE has no output builtin segment.")]
+    MissingOutputSegment,
+    #[error("Nonce of contract {0} does not fit in 64 bits : {1}.")]
+    NonceOutOfBounds(Felt252, Felt252),
+    #[error("Unsupported state diff compression version {0}.")]
+    UnsupportedCompressionVersion(usize),
+    #[error("Compressed state diff element {0} refers to a missing value.")]
+    MissingCompressedValue(usize),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SharpError {
+    #[error("Could not reach SHARP : {0}.")]
+    Network(#[source] reqwest::Error),
+    #[error("SHARP responded with HTTP {status} ({error_type:?}) : {error_message:?}.")]
+    Http {
+        status: u16,
+        error_type: Option<String>,
+        error_message: Option<String>,
+        stack_trace: Option<Vec<String>>,
+        body: String,
+    },
+    #[error("SHARP rejected the request ({error_type:?}) : {error_message:?}.")]
+    Rejected { error_type: Option<String>, error_message: Option<String>, stack_trace: Option<Vec<String>> },
+    #[error("Could not decode SHARP response {body} : {error}.")]
+    Decode {
+        body: String,
+        #[source]
+        error: serde_json::Error,
+    },
+    #[error("SHARP accepted the job without returning a job key.")]
+    MissingJobKey,
+    #[error("Invalid SHARP client configuration : {0}.")]
+    Config(String),
+}
+
+impl SharpError {
+    /// Whether the request may succeed when retried, i.e. SHARP was unreachable or overloaded.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            SharpError::Network(_) => true,
+            SharpError::Http { status, .. } => *status == 429 || *status >= 500,
+            _ => false,
+        }
+    }
 }
 
 #[derive(thiserror::Error, Clone, Debug)]
@@ -88,6 +88,16 @@ impl ExecutionHelperWrapper {
         self.start_tx(None);
         self.end_tx()
     }
+    /// Fee charged for the transaction being executed, `None` outside of a transaction.
+    pub fn actual_fee(&self) -> Option<Felt252> {
+        let eh_ref = self.execution_helper.as_ref().borrow();
+        eh_ref.tx_execution_info.as_ref().map(|tx_execution_info| Felt252::from(tx_execution_info.actual_fee.0))
+    }
+    /// Whether the transaction being executed was reverted, `None` outside of a transaction.
+    pub fn is_reverted(&self) -> Option<bool> {
+        let eh_ref = self.execution_helper.as_ref().borrow();
+        eh_ref.tx_execution_info.as_ref().map(|tx_execution_info| tx_execution_info.revert_error.is_some())
+    }
     pub fn enter_call(&self, execution_info_ptr: Option<Relocatable>) {
         let mut eh_ref = self.execution_helper.as_ref().borrow_mut();
         assert!(eh_ref.call_execution_info_ptr.is_none());
@@ -160,6 +170,8 @@ trait GenCallIter {
 impl GenCallIter for TransactionExecutionInfo {
     fn gen_call_iterator(&self) -> IntoIter<CallInfo> {
         let mut call_infos = vec![];
+        // blockifier drops the execute call info of a reverted transaction, so only its
+        // validate and fee transfer calls are left
         for call_info in self.non_optional_call_infos() {
             call_infos.extend(call_info.clone().gen_call_topology());
         }
@@ -176,7 +188,8 @@ impl GenCallTopology for CallInfo {
         // Create a vector to store the results
         let mut results = vec![self.clone()];
 
-        // Iterate over internal calls, recursively call gen_call_topology, and collect the results
+        // Iterate over internal calls, recursively call gen_call_topology, and collect the results.
+        // A failed call still has its inner calls collected: the OS runs it until it fails.
         for call in self.inner_calls.into_iter() {
             results.extend(call.gen_call_topology());
         }
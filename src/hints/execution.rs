@@ -10,7 +10,7 @@ use cairo_vm::hint_processor::builtin_hint_processor::hint_utils::{
 use cairo_vm::hint_processor::hint_processor_definition::HintReference;
 use cairo_vm::serde::deserialize_program::ApTracking;
 use cairo_vm::types::exec_scope::ExecutionScopes;
-use cairo_vm::types::relocatable::MaybeRelocatable;
+use cairo_vm::types::relocatable::{MaybeRelocatable, Relocatable};
 use cairo_vm::vm::errors::hint_errors::HintError;
 use cairo_vm::vm::vm_core::VirtualMachine;
 use cairo_vm::Felt252;
@@ -19,7 +19,7 @@ use indoc::indoc;
 use crate::execution::deprecated_syscall_handler::DeprecatedOsSyscallHandlerWrapper;
 use crate::execution::helper::ExecutionHelperWrapper;
 use crate::io::input::StarknetOsInput;
-use crate::io::InternalTransaction;
+use crate::io::{InternalDeclare, InternalTransaction};
 
 pub const LOAD_NEXT_TX: &str = indoc! {r#"
     tx = next(transactions)
@@ -38,7 +38,13 @@ pub fn load_next_tx(
     let tx = transactions.next().unwrap();
     exec_scopes.insert_value("transactions", transactions);
     exec_scopes.insert_value("tx", tx.clone());
-    insert_value_from_var_name("tx_type", Felt252::from_bytes_be_slice(tx.r#type.as_bytes()), vm, ids_data, ap_tracking)
+    insert_value_from_var_name(
+        "tx_type",
+        Felt252::from_bytes_be_slice(tx.tx_type().as_bytes()),
+        vm,
+        ids_data,
+        ap_tracking,
+    )
 }
 
 pub const PREPARE_CONSTRUCTOR_EXECUTION: &str = indoc! {r#"
@@ -55,30 +61,20 @@ pub fn prepare_constructor_execution(
     _constants: &HashMap<String, Felt252>,
 ) -> Result<(), HintError> {
     let tx = exec_scopes.get::<InternalTransaction>("tx")?;
-    insert_value_from_var_name(
-        "contract_address_salt",
-        tx.contract_address_salt.expect("`contract_address_salt` must be present"),
-        vm,
-        ids_data,
-        ap_tracking,
-    )?;
-    insert_value_from_var_name(
-        "class_hash",
-        // using `contract_hash` instead of `class_hash` as the that's how the
-        // input.json is structured
-        tx.contract_hash.expect("`contract_hash` must be present"),
-        vm,
-        ids_data,
-        ap_tracking,
-    )?;
-
-    let constructor_calldata_size = match &tx.constructor_calldata {
-        None => 0,
-        Some(calldata) => calldata.len(),
+    let (contract_address_salt, class_hash, constructor_calldata) = match tx {
+        InternalTransaction::Deploy(tx) => (tx.contract_address_salt, tx.class_hash, tx.constructor_calldata),
+        InternalTransaction::DeployAccount(tx) => (tx.contract_address_salt, tx.class_hash, tx.constructor_calldata),
+        _ => {
+            return Err(HintError::CustomHint(
+                format!("{} transaction has no constructor to execute", tx.tx_type()).into_boxed_str(),
+            ));
+        }
     };
-    insert_value_from_var_name("constructor_calldata_size", constructor_calldata_size, vm, ids_data, ap_tracking)?;
+    insert_value_from_var_name("contract_address_salt", contract_address_salt, vm, ids_data, ap_tracking)?;
+    insert_value_from_var_name("class_hash", class_hash, vm, ids_data, ap_tracking)?;
+    insert_value_from_var_name("constructor_calldata_size", constructor_calldata.len(), vm, ids_data, ap_tracking)?;
 
-    let constructor_calldata = tx.constructor_calldata.unwrap_or_default().iter().map(|felt| felt.into()).collect();
+    let constructor_calldata = constructor_calldata.iter().map(|felt| felt.into()).collect();
     let constructor_calldata_base = vm.add_memory_segment();
     vm.load_data(constructor_calldata_base, &constructor_calldata)?;
     insert_value_from_var_name("constructor_calldata", constructor_calldata_base, vm, ids_data, ap_tracking)
@@ -93,7 +89,7 @@ pub fn transaction_version(
     _constants: &HashMap<String, Felt252>,
 ) -> Result<(), HintError> {
     let tx = exec_scopes.get::<InternalTransaction>("tx")?;
-    insert_value_into_ap(vm, tx.version.expect("Transaction version should be set"))
+    insert_value_into_ap(vm, tx.version())
 }
 
 pub const ASSERT_TRANSACTION_HASH: &str = indoc! {r#"
@@ -111,15 +107,202 @@ pub fn assert_transaction_hash(
     let tx = exec_scopes.get::<InternalTransaction>("tx")?;
     let transaction_hash = get_integer_from_var_name("transaction_hash", vm, ids_data, ap_tracking)?.into_owned();
 
+    let hash_value = tx.hash_value();
     assert_eq!(
-        tx.hash_value, transaction_hash,
+        hash_value, transaction_hash,
         "Computed transaction_hash is inconsistent with the hash in the transaction. Computed hash = {}, Expected \
          hash = {}.",
-        transaction_hash, tx.hash_value
+        transaction_hash, hash_value
     );
     Ok(())
 }
 
+pub const TX_NONCE: &str = "memory[ap] = to_felt_or_relocatable(0 if tx.nonce is None else tx.nonce)";
+pub fn tx_nonce(
+    vm: &mut VirtualMachine,
+    exec_scopes: &mut ExecutionScopes,
+    _ids_data: &HashMap<String, HintReference>,
+    _ap_tracking: &ApTracking,
+    _constants: &HashMap<String, Felt252>,
+) -> Result<(), HintError> {
+    let tx = exec_scopes.get::<InternalTransaction>("tx")?;
+    insert_value_into_ap(vm, tx.nonce().unwrap_or(Felt252::ZERO))
+}
+
+pub const TX_NONCE_ARG: &str = "memory[ap] = to_felt_or_relocatable(tx.nonce)";
+pub fn tx_nonce_arg(
+    vm: &mut VirtualMachine,
+    exec_scopes: &mut ExecutionScopes,
+    _ids_data: &HashMap<String, HintReference>,
+    _ap_tracking: &ApTracking,
+    _constants: &HashMap<String, Felt252>,
+) -> Result<(), HintError> {
+    let tx = exec_scopes.get::<InternalTransaction>("tx")?;
+    insert_value_into_ap(vm, tx_field(&tx, tx.nonce(), "nonce")?)
+}
+
+pub const TX_ADDITIONAL_DATA_NONCE: &str = "memory[ap] = to_felt_or_relocatable(segments.gen_arg([tx.nonce]))";
+pub fn tx_additional_data_nonce(
+    vm: &mut VirtualMachine,
+    exec_scopes: &mut ExecutionScopes,
+    _ids_data: &HashMap<String, HintReference>,
+    _ap_tracking: &ApTracking,
+    _constants: &HashMap<String, Felt252>,
+) -> Result<(), HintError> {
+    let tx = exec_scopes.get::<InternalTransaction>("tx")?;
+    let additional_data = gen_felt_arg(vm, &[tx_field(&tx, tx.nonce(), "nonce")?])?;
+    insert_value_into_ap(vm, additional_data)
+}
+
+pub const TX_MAX_FEE: &str = "memory[ap] = to_felt_or_relocatable(tx.max_fee)";
+pub fn tx_max_fee(
+    vm: &mut VirtualMachine,
+    exec_scopes: &mut ExecutionScopes,
+    _ids_data: &HashMap<String, HintReference>,
+    _ap_tracking: &ApTracking,
+    _constants: &HashMap<String, Felt252>,
+) -> Result<(), HintError> {
+    let tx = exec_scopes.get::<InternalTransaction>("tx")?;
+    insert_value_into_ap(vm, tx_field(&tx, tx.max_fee(), "max_fee")?)
+}
+
+pub const TX_ENTRY_POINT_SELECTOR: &str = "memory[ap] = to_felt_or_relocatable(tx.entry_point_selector)";
+pub fn tx_entry_point_selector(
+    vm: &mut VirtualMachine,
+    exec_scopes: &mut ExecutionScopes,
+    _ids_data: &HashMap<String, HintReference>,
+    _ap_tracking: &ApTracking,
+    _constants: &HashMap<String, Felt252>,
+) -> Result<(), HintError> {
+    let tx = exec_scopes.get::<InternalTransaction>("tx")?;
+    insert_value_into_ap(vm, tx_field(&tx, tx.entry_point_selector(), "entry_point_selector")?)
+}
+
+pub const TX_CALLDATA_LEN: &str = "memory[ap] = to_felt_or_relocatable(len(tx.calldata))";
+pub fn tx_calldata_len(
+    vm: &mut VirtualMachine,
+    exec_scopes: &mut ExecutionScopes,
+    _ids_data: &HashMap<String, HintReference>,
+    _ap_tracking: &ApTracking,
+    _constants: &HashMap<String, Felt252>,
+) -> Result<(), HintError> {
+    let tx = exec_scopes.get::<InternalTransaction>("tx")?;
+    insert_value_into_ap(vm, tx_field(&tx, tx.calldata(), "calldata")?.len())
+}
+
+pub const TX_CALLDATA: &str = "memory[ap] = to_felt_or_relocatable(segments.gen_arg(tx.calldata))";
+pub fn tx_calldata(
+    vm: &mut VirtualMachine,
+    exec_scopes: &mut ExecutionScopes,
+    _ids_data: &HashMap<String, HintReference>,
+    _ap_tracking: &ApTracking,
+    _constants: &HashMap<String, Felt252>,
+) -> Result<(), HintError> {
+    let tx = exec_scopes.get::<InternalTransaction>("tx")?;
+    let calldata = gen_felt_arg(vm, tx_field(&tx, tx.calldata(), "calldata")?)?;
+    insert_value_into_ap(vm, calldata)
+}
+
+pub const TX_SIGNATURE: &str = indoc! {r#"
+    ids.signature_start = segments.gen_arg(arg=tx.signature)
+    ids.signature_len = len(tx.signature)"#
+};
+pub fn tx_signature(
+    vm: &mut VirtualMachine,
+    exec_scopes: &mut ExecutionScopes,
+    ids_data: &HashMap<String, HintReference>,
+    ap_tracking: &ApTracking,
+    _constants: &HashMap<String, Felt252>,
+) -> Result<(), HintError> {
+    let tx = exec_scopes.get::<InternalTransaction>("tx")?;
+    let signature = tx_field(&tx, tx.signature(), "signature")?;
+    let signature_start = gen_felt_arg(vm, signature)?;
+    insert_value_from_var_name("signature_start", signature_start, vm, ids_data, ap_tracking)?;
+    insert_value_from_var_name("signature_len", signature.len(), vm, ids_data, ap_tracking)
+}
+
+pub const IS_L1_HANDLER_CONTRACT_ADDRESS: &str = indoc! {r#"
+    from starkware.starknet.business_logic.transaction.objects import InternalL1Handler
+    ids.contract_address = (
+        tx.contract_address if isinstance(tx, InternalL1Handler) else tx.sender_address
+    )"#
+};
+pub fn is_l1_handler_contract_address(
+    vm: &mut VirtualMachine,
+    exec_scopes: &mut ExecutionScopes,
+    ids_data: &HashMap<String, HintReference>,
+    ap_tracking: &ApTracking,
+    _constants: &HashMap<String, Felt252>,
+) -> Result<(), HintError> {
+    let tx = exec_scopes.get::<InternalTransaction>("tx")?;
+    let contract_address = match &tx {
+        InternalTransaction::L1Handler(tx) => tx.contract_address,
+        _ => tx_field(&tx, tx.sender_address(), "sender_address")?,
+    };
+    insert_value_from_var_name("contract_address", contract_address, vm, ids_data, ap_tracking)
+}
+
+pub const DECLARE_TX_FIELDS: &str = indoc! {r#"
+    ids.tx_version = tx.version
+    ids.max_fee = tx.max_fee
+    ids.sender_address = tx.sender_address
+    ids.calldata = segments.gen_arg([tx.class_hash])
+
+    if tx.version <= 1:
+        assert tx.compiled_class_hash is None, (
+            "Deprecated declare must not have compiled_class_hash."
+        )
+        ids.additional_data = segments.gen_arg([tx.nonce])
+    else:
+        assert tx.compiled_class_hash is not None, (
+            "Declare must have a concrete compiled_class_hash."
+        )
+        ids.additional_data = segments.gen_arg([tx.nonce, tx.compiled_class_hash])"#
+};
+pub fn declare_tx_fields(
+    vm: &mut VirtualMachine,
+    exec_scopes: &mut ExecutionScopes,
+    ids_data: &HashMap<String, HintReference>,
+    ap_tracking: &ApTracking,
+    _constants: &HashMap<String, Felt252>,
+) -> Result<(), HintError> {
+    let tx = exec_scopes.get::<InternalTransaction>("tx")?;
+    let InternalTransaction::Declare(declare) = &tx else {
+        return Err(HintError::CustomHint(
+            format!("Expected a DECLARE transaction, got {}", tx.tx_type()).into_boxed_str(),
+        ));
+    };
+    let (sender_address, class_hash, additional_data) = match declare {
+        InternalDeclare::V0(declare) | InternalDeclare::V1(declare) => {
+            (declare.sender_address, declare.class_hash, vec![declare.nonce])
+        }
+        InternalDeclare::V2(declare) => {
+            (declare.sender_address, declare.class_hash, vec![declare.nonce, declare.compiled_class_hash])
+        }
+    };
+
+    insert_value_from_var_name("tx_version", tx.version(), vm, ids_data, ap_tracking)?;
+    insert_value_from_var_name("max_fee", tx_field(&tx, tx.max_fee(), "max_fee")?, vm, ids_data, ap_tracking)?;
+    insert_value_from_var_name("sender_address", sender_address, vm, ids_data, ap_tracking)?;
+    let calldata = gen_felt_arg(vm, &[class_hash])?;
+    insert_value_from_var_name("calldata", calldata, vm, ids_data, ap_tracking)?;
+    let additional_data = gen_felt_arg(vm, &additional_data)?;
+    insert_value_from_var_name("additional_data", additional_data, vm, ids_data, ap_tracking)
+}
+
+/// Unwraps a field that is only carried by some transaction types.
+fn tx_field<T>(tx: &InternalTransaction, field: Option<T>, name: &str) -> Result<T, HintError> {
+    field.ok_or_else(|| HintError::CustomHint(format!("{} transaction has no {name}", tx.tx_type()).into_boxed_str()))
+}
+
+/// Equivalent of `segments.gen_arg` for a list of felts.
+fn gen_felt_arg(vm: &mut VirtualMachine, felts: &[Felt252]) -> Result<Relocatable, HintError> {
+    let data: Vec<MaybeRelocatable> = felts.iter().map(|felt| felt.into()).collect();
+    let base = vm.add_memory_segment();
+    vm.load_data(base, &data)?;
+    Ok(base)
+}
+
 pub const ENTER_SCOPE_SYSCALL_HANDLER: &str = "vm_enter_scope({'syscall_handler': deprecated_syscall_handler})";
 pub fn enter_scope_syscall_handler(
     _vm: &mut VirtualMachine,
@@ -254,15 +437,94 @@ pub fn start_deploy_tx(
     ap_tracking: &ApTracking,
     _constants: &HashMap<String, Felt252>,
 ) -> Result<(), HintError> {
-    let constructor_execution_context =
-        get_relocatable_from_var_name("constructor_execution_context", vm, ids_data, ap_tracking)?;
-    let deprecated_tx_info_ptr = (constructor_execution_context + 5usize).unwrap();
+    let deprecated_tx_info_ptr =
+        get_deprecated_tx_info_ptr("constructor_execution_context", vm, ids_data, ap_tracking)?;
+
+    let execution_helper = exec_scopes.get::<ExecutionHelperWrapper>("execution_helper")?;
+    execution_helper.start_tx(Some(deprecated_tx_info_ptr));
+    Ok(())
+}
+
+pub const START_VALIDATE_DECLARE_TX: &str = indoc! {r#"
+    execution_helper.start_tx(
+        tx_info_ptr=ids.validate_declare_execution_context.deprecated_tx_info.address_
+    )"#
+};
+pub fn start_validate_declare_tx(
+    vm: &mut VirtualMachine,
+    exec_scopes: &mut ExecutionScopes,
+    ids_data: &HashMap<String, HintReference>,
+    ap_tracking: &ApTracking,
+    _constants: &HashMap<String, Felt252>,
+) -> Result<(), HintError> {
+    let deprecated_tx_info_ptr =
+        get_deprecated_tx_info_ptr("validate_declare_execution_context", vm, ids_data, ap_tracking)?;
+
+    let execution_helper = exec_scopes.get::<ExecutionHelperWrapper>("execution_helper")?;
+    execution_helper.start_tx(Some(deprecated_tx_info_ptr));
+    Ok(())
+}
+
+pub const START_TX: &str = "execution_helper.start_tx(tx_info_ptr=ids.deprecated_tx_info.address_)";
+pub fn start_tx(
+    vm: &mut VirtualMachine,
+    exec_scopes: &mut ExecutionScopes,
+    ids_data: &HashMap<String, HintReference>,
+    ap_tracking: &ApTracking,
+    _constants: &HashMap<String, Felt252>,
+) -> Result<(), HintError> {
+    let deprecated_tx_info_ptr = get_ptr_from_var_name("deprecated_tx_info", vm, ids_data, ap_tracking)?;
+
+    let execution_helper = exec_scopes.get::<ExecutionHelperWrapper>("execution_helper")?;
+    execution_helper.start_tx(Some(deprecated_tx_info_ptr));
+    Ok(())
+}
+
+pub const START_INVOKE_TX: &str = indoc! {r#"
+    tx_info_ptr = ids.tx_execution_context.deprecated_tx_info.address_
+    execution_helper.start_tx(tx_info_ptr=tx_info_ptr)"#
+};
+pub fn start_invoke_tx(
+    vm: &mut VirtualMachine,
+    exec_scopes: &mut ExecutionScopes,
+    ids_data: &HashMap<String, HintReference>,
+    ap_tracking: &ApTracking,
+    _constants: &HashMap<String, Felt252>,
+) -> Result<(), HintError> {
+    let deprecated_tx_info_ptr = get_deprecated_tx_info_ptr("tx_execution_context", vm, ids_data, ap_tracking)?;
 
-    let execution_helper = exec_scopes.get::<ExecutionHelperWrapper>("execution_helper").unwrap();
+    let execution_helper = exec_scopes.get::<ExecutionHelperWrapper>("execution_helper")?;
     execution_helper.start_tx(Some(deprecated_tx_info_ptr));
     Ok(())
 }
 
+/// Reads `ExecutionContext.deprecated_tx_info` from the execution context pointed to by `var_name`.
+///
+/// The execution contexts are `ExecutionContext*` in the OS, so `deprecated_tx_info.address_` is
+/// the pointer stored in the context, not the address of the member itself.
+fn get_deprecated_tx_info_ptr(
+    var_name: &str,
+    vm: &VirtualMachine,
+    ids_data: &HashMap<String, HintReference>,
+    ap_tracking: &ApTracking,
+) -> Result<Relocatable, HintError> {
+    let execution_context = get_ptr_from_var_name(var_name, vm, ids_data, ap_tracking)?;
+    Ok(vm.get_relocatable((execution_context + 5usize)?)?)
+}
+
+pub const SKIP_TX: &str = "execution_helper.skip_tx()";
+pub fn skip_tx(
+    _vm: &mut VirtualMachine,
+    exec_scopes: &mut ExecutionScopes,
+    _ids_data: &HashMap<String, HintReference>,
+    _ap_tracking: &ApTracking,
+    _constants: &HashMap<String, Felt252>,
+) -> Result<(), HintError> {
+    let execution_helper = exec_scopes.get::<ExecutionHelperWrapper>("execution_helper")?;
+    execution_helper.skip_tx();
+    Ok(())
+}
+
 pub const END_TX: &str = "execution_helper.end_tx()";
 pub fn end_tx(
     _vm: &mut VirtualMachine,
@@ -307,3 +569,59 @@ pub fn exit_call(
     execution_helper.exit_call();
     Ok(())
 }
+
+pub const SKIP_CALL: &str = "execution_helper.skip_call()";
+pub fn skip_call(
+    _vm: &mut VirtualMachine,
+    exec_scopes: &mut ExecutionScopes,
+    _ids_data: &HashMap<String, HintReference>,
+    _ap_tracking: &ApTracking,
+    _constants: &HashMap<String, Felt252>,
+) -> Result<(), HintError> {
+    let mut execution_helper = exec_scopes.get::<ExecutionHelperWrapper>("execution_helper")?;
+    execution_helper.skip_call();
+    Ok(())
+}
+
+pub const TX_ACTUAL_FEE: &str = "memory[ap] = to_felt_or_relocatable(execution_helper.tx_execution_info.actual_fee)";
+pub fn tx_actual_fee(
+    vm: &mut VirtualMachine,
+    exec_scopes: &mut ExecutionScopes,
+    _ids_data: &HashMap<String, HintReference>,
+    _ap_tracking: &ApTracking,
+    _constants: &HashMap<String, Felt252>,
+) -> Result<(), HintError> {
+    let execution_helper = exec_scopes.get::<ExecutionHelperWrapper>("execution_helper")?;
+    let actual_fee = execution_helper.actual_fee().ok_or_else(no_tx_execution_info)?;
+    insert_value_into_ap(vm, actual_fee)
+}
+
+pub const TX_IS_REVERTED: &str = "memory[ap] = to_felt_or_relocatable(execution_helper.tx_execution_info.is_reverted)";
+pub fn tx_is_reverted(
+    vm: &mut VirtualMachine,
+    exec_scopes: &mut ExecutionScopes,
+    _ids_data: &HashMap<String, HintReference>,
+    _ap_tracking: &ApTracking,
+    _constants: &HashMap<String, Felt252>,
+) -> Result<(), HintError> {
+    let execution_helper = exec_scopes.get::<ExecutionHelperWrapper>("execution_helper")?;
+    let is_reverted = execution_helper.is_reverted().ok_or_else(no_tx_execution_info)?;
+    insert_value_into_ap(vm, Felt252::from(u8::from(is_reverted)))
+}
+
+pub const IS_ENOUGH_GAS: &str = "memory[ap] = to_felt_or_relocatable(ids.initial_gas >= ids.required_gas)";
+pub fn is_enough_gas(
+    vm: &mut VirtualMachine,
+    _exec_scopes: &mut ExecutionScopes,
+    ids_data: &HashMap<String, HintReference>,
+    ap_tracking: &ApTracking,
+    _constants: &HashMap<String, Felt252>,
+) -> Result<(), HintError> {
+    let initial_gas = get_integer_from_var_name("initial_gas", vm, ids_data, ap_tracking)?.into_owned();
+    let required_gas = get_integer_from_var_name("required_gas", vm, ids_data, ap_tracking)?.into_owned();
+    insert_value_into_ap(vm, Felt252::from(u8::from(initial_gas >= required_gas)))
+}
+
+fn no_tx_execution_info() -> HintError {
+    HintError::CustomHint("No transaction is being executed".to_string().into_boxed_str())
+}
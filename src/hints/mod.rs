@@ -32,7 +32,7 @@ type HintImpl = fn(
     &HashMap<String, Felt252>,
 ) -> Result<(), HintError>;
 
-static HINTS: [(&str, HintImpl); 49] = [
+static HINTS: [(&str, HintImpl); 67] = [
     // (BREAKPOINT, breakpoint),
     (STARKNET_OS_INPUT, starknet_os_input),
     (INITIALIZE_STATE_CHANGES, initialize_state_changes),
@@ -62,10 +62,28 @@ static HINTS: [(&str, HintImpl); 49] = [
     (execution::TRANSACTION_VERSION, execution::transaction_version),
     (execution::ASSERT_TRANSACTION_HASH, execution::assert_transaction_hash),
     (execution::ENTER_SCOPE_SYSCALL_HANDLER, execution::enter_scope_syscall_handler),
+    (execution::TX_NONCE, execution::tx_nonce),
+    (execution::TX_NONCE_ARG, execution::tx_nonce_arg),
+    (execution::TX_ADDITIONAL_DATA_NONCE, execution::tx_additional_data_nonce),
+    (execution::TX_MAX_FEE, execution::tx_max_fee),
+    (execution::TX_ENTRY_POINT_SELECTOR, execution::tx_entry_point_selector),
+    (execution::TX_CALLDATA_LEN, execution::tx_calldata_len),
+    (execution::TX_CALLDATA, execution::tx_calldata),
+    (execution::TX_SIGNATURE, execution::tx_signature),
+    (execution::IS_L1_HANDLER_CONTRACT_ADDRESS, execution::is_l1_handler_contract_address),
+    (execution::DECLARE_TX_FIELDS, execution::declare_tx_fields),
     (execution::START_DEPLOY_TX, execution::start_deploy_tx),
+    (execution::START_VALIDATE_DECLARE_TX, execution::start_validate_declare_tx),
+    (execution::START_TX, execution::start_tx),
+    (execution::START_INVOKE_TX, execution::start_invoke_tx),
+    (execution::SKIP_TX, execution::skip_tx),
     (execution::END_TX, execution::end_tx),
     (execution::ENTER_CALL, execution::enter_call),
     (execution::EXIT_CALL, execution::exit_call),
+    (execution::SKIP_CALL, execution::skip_call),
+    (execution::TX_ACTUAL_FEE, execution::tx_actual_fee),
+    (execution::TX_IS_REVERTED, execution::tx_is_reverted),
+    (execution::IS_ENOUGH_GAS, execution::is_enough_gas),
     (syscalls::CALL_CONTRACT, syscalls::call_contract),
     (syscalls::DELEGATE_CALL, syscalls::delegate_call),
     (syscalls::DELEGATE_L1_HANDLER, syscalls::delegate_l1_handler),
@@ -138,6 +156,11 @@ impl Default for SnosHintProcessor {
 }
 
 impl SnosHintProcessor {
+    /// Hint processor that stops the run after `max_steps` VM steps.
+    pub fn with_max_steps(max_steps: usize) -> Self {
+        Self { run_resources: RunResources::new(max_steps), ..Self::default() }
+    }
+
     pub fn hints(&self) -> HashSet<String> {
         self.hints
             .keys()
@@ -202,12 +225,19 @@ pub fn starknet_os_input(
     ap_tracking: &ApTracking,
     _constants: &HashMap<String, Felt252>,
 ) -> Result<(), HintError> {
-    let input_path =
-        std::path::PathBuf::from(exec_scopes.get::<String>("input_path").unwrap_or(DEFAULT_INPUT_PATH.to_string()));
-
-    let os_input = Box::new(
-        StarknetOsInput::load(&input_path).map_err(|e| HintError::CustomHint(e.to_string().into_boxed_str()))?,
-    );
+    // An input provided by the runner takes precedence over the one stored at `input_path`.
+    let os_input = match exec_scopes.get::<StarknetOsInput>("os_input") {
+        Ok(os_input) => Box::new(os_input),
+        Err(_) => {
+            let input_path = std::path::PathBuf::from(
+                exec_scopes.get::<String>("input_path").unwrap_or(DEFAULT_INPUT_PATH.to_string()),
+            );
+            Box::new(
+                StarknetOsInput::load(&input_path)
+                    .map_err(|e| HintError::CustomHint(e.to_string().into_boxed_str()))?,
+            )
+        }
+    };
     exec_scopes.assign_or_update_variable("os_input", os_input);
 
     let initial_carried_outputs_ptr = get_ptr_from_var_name("initial_carried_outputs", vm, ids_data, ap_tracking)?;
@@ -0,0 +1,243 @@
+use cairo_vm::Felt252;
+use indexmap::IndexSet;
+
+use super::output::{ClassDeclaration, ContractUpdate, OutputReader, StarknetOsOutput};
+use crate::error::{OutputError, SnOsError};
+
+/// Parameters of the state diff compression, see `compression.py` in cairo-lang.
+const COMPRESSION_VERSION: usize = 0;
+const MAX_N_BITS: usize = 251;
+/// Bit sizes of the unique value buckets, a value goes to the smallest bucket it fits in.
+const N_BITS_PER_BUCKET: [u32; 6] = [252, 125, 83, 62, 31, 15];
+/// The unique value buckets followed by the bucket of repeating values.
+const TOTAL_N_BUCKETS: usize = N_BITS_PER_BUCKET.len() + 1;
+const HEADER_ELM_BOUND: u128 = 1 << 20;
+/// Version, data length and the length of every bucket.
+const HEADER_LEN: usize = 2 + TOTAL_N_BUCKETS;
+
+/// State diff of a block as posted on L1 for data availability.
+///
+/// The encoding is the one of the OS output: the number of updated contracts followed by each
+/// contract (address, packed class flag/nonce/number of updates, optional class hash and storage
+/// pairs), then the number of declared classes followed by `(class_hash, compiled_class_hash)`
+/// pairs. This is the uncompressed format of the OS output, [DataAvailability::compress] gives the
+/// data posted in the blob. The KZG commitment of the blob is not computed here.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DataAvailability {
+    pub contract_updates: Vec<ContractUpdate>,
+    pub class_declarations: Vec<ClassDeclaration>,
+}
+
+impl DataAvailability {
+    pub fn from_output(os_output: &StarknetOsOutput) -> Result<Self, SnOsError> {
+        Ok(Self {
+            contract_updates: os_output.decode_contract_updates()?,
+            class_declarations: os_output.decode_class_declarations()?,
+        })
+    }
+
+    pub fn encode(&self) -> Result<Vec<Felt252>, SnOsError> {
+        let mut encoded = vec![Felt252::from(self.contract_updates.len())];
+        for contract_update in &self.contract_updates {
+            encoded.extend(contract_update.encode()?);
+        }
+
+        encoded.push(Felt252::from(self.class_declarations.len()));
+        encoded.extend(
+            self.class_declarations
+                .iter()
+                .flat_map(|declaration| [declaration.class_hash, declaration.compiled_class_hash]),
+        );
+
+        Ok(encoded)
+    }
+
+    /// Decodes an encoding produced by [DataAvailability::encode].
+    pub fn decode(encoded: &[Felt252]) -> Result<Self, SnOsError> {
+        let mut reader = OutputReader::new(encoded);
+
+        let n_contracts = reader.read_len()?;
+        let contract_updates: Vec<ContractUpdate> =
+            (0..n_contracts).map(|_| reader.read_contract_update()).collect::<Result<_, _>>()?;
+        let class_declarations = reader
+            .read_pairs()?
+            .chunks_exact(2)
+            .map(|pair| ClassDeclaration { class_hash: pair[0], compiled_class_hash: pair[1] })
+            .collect();
+        reader.finish()?;
+
+        Ok(Self { contract_updates, class_declarations })
+    }
+
+    /// Compressed encoding, see [compress].
+    pub fn compress(&self) -> Result<Vec<Felt252>, SnOsError> {
+        Ok(compress(&self.encode()?))
+    }
+
+    /// Decodes a compressed encoding produced by [DataAvailability::compress].
+    pub fn decompress(compressed: &[Felt252]) -> Result<Self, SnOsError> {
+        Self::decode(&decompress(compressed)?)
+    }
+}
+
+/// Compresses `data` the way the OS does before posting the state diff in a blob.
+///
+/// Values are sorted into buckets by bit size and the unique values of each bucket are packed
+/// together; a value seen before is replaced by a pointer to its first occurrence. The bucket of
+/// every element is packed at the end so that the original order can be restored.
+pub fn compress(data: &[Felt252]) -> Vec<Felt252> {
+    let mut buckets = vec![IndexSet::new(); N_BITS_PER_BUCKET.len()];
+    let mut repeating_value_locations = vec![];
+    let mut bucket_index_per_elm = vec![];
+    for value in data {
+        let bucket_index = (0..N_BITS_PER_BUCKET.len())
+            .rev()
+            .find(|bucket_index| fits_in_bucket(value, N_BITS_PER_BUCKET[*bucket_index]))
+            .expect("the largest bucket holds any felt");
+        let (index_in_bucket, is_new) = buckets[bucket_index].insert_full(*value);
+        if is_new {
+            bucket_index_per_elm.push(Felt252::from(bucket_index));
+        } else {
+            repeating_value_locations.push((bucket_index, index_in_bucket));
+            bucket_index_per_elm.push(Felt252::from(N_BITS_PER_BUCKET.len()));
+        }
+    }
+
+    let bucket_lengths: Vec<usize> = buckets.iter().map(IndexSet::len).collect();
+    let bucket_offsets = bucket_offsets(&bucket_lengths);
+    let n_unique_values: usize = bucket_lengths.iter().sum();
+
+    let mut header = vec![Felt252::from(COMPRESSION_VERSION), Felt252::from(data.len())];
+    header.extend(bucket_lengths.iter().map(|len| Felt252::from(*len)));
+    header.push(Felt252::from(repeating_value_locations.len()));
+    let repeating_value_pointers: Vec<Felt252> = repeating_value_locations
+        .iter()
+        .map(|(bucket_index, index_in_bucket)| Felt252::from(bucket_offsets[*bucket_index] + index_in_bucket))
+        .collect();
+
+    let mut compressed = pack_in_felts(&header, Some(HEADER_ELM_BOUND));
+    for (bucket, n_bits) in buckets.iter().zip(N_BITS_PER_BUCKET) {
+        compressed.extend(pack_in_felts(&bucket.iter().copied().collect::<Vec<_>>(), bucket_bound(n_bits)));
+    }
+    compressed.extend(pack_in_felts(&repeating_value_pointers, Some(n_unique_values as u128)));
+    compressed.extend(pack_in_felts(&bucket_index_per_elm, Some(TOTAL_N_BUCKETS as u128)));
+    compressed
+}
+
+/// Restores the data compressed by [compress].
+pub fn decompress(compressed: &[Felt252]) -> Result<Vec<Felt252>, OutputError> {
+    let mut reader = OutputReader::new(compressed);
+
+    let header = read_packed_usizes(&mut reader, HEADER_LEN, Some(HEADER_ELM_BOUND))?;
+    if header[0] != COMPRESSION_VERSION {
+        return Err(OutputError::UnsupportedCompressionVersion(header[0]));
+    }
+    let data_len = header[1];
+    let bucket_lengths = &header[2..];
+
+    let mut all_values = vec![];
+    for (len, n_bits) in bucket_lengths.iter().zip(N_BITS_PER_BUCKET) {
+        all_values.extend(read_packed(&mut reader, *len, bucket_bound(n_bits))?);
+    }
+    let n_unique_values = all_values.len();
+    let n_repeating_values = bucket_lengths[N_BITS_PER_BUCKET.len()];
+    // without unique values there are no pointers, a bound of one packs them the same way
+    let pointer_bound = n_unique_values.max(1) as u128;
+    for (index, pointer) in
+        read_packed_usizes(&mut reader, n_repeating_values, Some(pointer_bound))?.into_iter().enumerate()
+    {
+        let value = all_values[..n_unique_values]
+            .get(pointer)
+            .copied()
+            .ok_or(OutputError::MissingCompressedValue(n_unique_values + index))?;
+        all_values.push(value);
+    }
+
+    let bucket_index_per_elm = read_packed_usizes(&mut reader, data_len, Some(TOTAL_N_BUCKETS as u128))?;
+    reader.finish()?;
+
+    let bucket_offsets = bucket_offsets(bucket_lengths);
+    let mut next_in_bucket = vec![0; TOTAL_N_BUCKETS];
+    bucket_index_per_elm
+        .into_iter()
+        .enumerate()
+        .map(|(index, bucket_index)| {
+            let next = next_in_bucket.get_mut(bucket_index).filter(|next| **next < bucket_lengths[bucket_index]);
+            let next = next.ok_or(OutputError::MissingCompressedValue(index))?;
+            let value = all_values[bucket_offsets[bucket_index] + *next];
+            *next += 1;
+            Ok(value)
+        })
+        .collect()
+}
+
+/// Bound of the values of a bucket, `None` when the values are full felts.
+fn bucket_bound(n_bits: u32) -> Option<u128> {
+    1u128.checked_shl(n_bits)
+}
+
+fn fits_in_bucket(value: &Felt252, n_bits: u32) -> bool {
+    match bucket_bound(n_bits) {
+        Some(bound) => *value < Felt252::from(bound),
+        None => true,
+    }
+}
+
+fn bucket_offsets(bucket_lengths: &[usize]) -> Vec<usize> {
+    bucket_lengths
+        .iter()
+        .scan(0, |offset, len| {
+            let bucket_offset = *offset;
+            *offset += len;
+            Some(bucket_offset)
+        })
+        .collect()
+}
+
+fn n_elms_per_felt(elm_bound: Option<u128>) -> usize {
+    match elm_bound {
+        None => 1,
+        Some(bound) if bound <= 1 => MAX_N_BITS,
+        Some(bound) => MAX_N_BITS / (u128::BITS - (bound - 1).leading_zeros()) as usize,
+    }
+}
+
+/// Packs elements below `elm_bound` as digits in base `elm_bound`, the first element being the
+/// least significant digit.
+fn pack_in_felts(elms: &[Felt252], elm_bound: Option<u128>) -> Vec<Felt252> {
+    let base = Felt252::from(elm_bound.unwrap_or_default());
+    elms.chunks(n_elms_per_felt(elm_bound))
+        .map(|chunk| chunk.iter().rev().fold(Felt252::ZERO, |packed, elm| packed * base + *elm))
+        .collect()
+}
+
+/// Reads `n_elms` elements packed by [pack_in_felts].
+fn read_packed(reader: &mut OutputReader, n_elms: usize, elm_bound: Option<u128>) -> Result<Vec<Felt252>, OutputError> {
+    let n_elms_per_felt = n_elms_per_felt(elm_bound);
+    let packed = reader.read_slice(n_elms.div_ceil(n_elms_per_felt))?;
+    let Some(bound) = elm_bound else {
+        return Ok(packed.to_vec());
+    };
+
+    let mut elms = vec![];
+    for felt in packed {
+        let mut packed = felt.to_biguint();
+        for _ in 0..n_elms_per_felt.min(n_elms - elms.len()) {
+            elms.push(Felt252::from_bytes_be_slice(&(&packed % bound).to_bytes_be()));
+            packed /= bound;
+        }
+    }
+    Ok(elms)
+}
+
+fn read_packed_usizes(
+    reader: &mut OutputReader,
+    n_elms: usize,
+    elm_bound: Option<u128>,
+) -> Result<Vec<usize>, OutputError> {
+    let offset = reader.offset();
+    read_packed(reader, n_elms, elm_bound)?
+        .into_iter()
+        .map(|elm| elm.to_bigint().try_into().map_err(|_| OutputError::InvalidLength(offset, elm)))
+        .collect()
+}
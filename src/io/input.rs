@@ -6,12 +6,13 @@ use cairo_vm::Felt252;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use starknet_api::deprecated_contract_class::ContractClass as DeprecatedContractClass;
+use starknet_api::hash::pedersen_hash;
 
 use super::InternalTransaction;
 use crate::config::StarknetGeneralConfig;
-use crate::error::SnOsError;
+use crate::error::{CommitmentInfoError, OsInputError, SnOsError};
 use crate::state::trie::{MerkleTrie, StarkHasher};
-use crate::utils::{Felt252HexNoPrefix, Felt252Num, Felt252Str, Felt252StrDec};
+use crate::utils::{felt_api2vm, felt_vm2api, Felt252HexNoPrefix, Felt252Num, Felt252Str, Felt252StrDec};
 
 #[serde_as]
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -45,7 +46,38 @@ impl StarknetOsInput {
 
         Ok(())
     }
+
+    /// Checks the consistency of the input before it is handed to the OS.
+    ///
+    /// Validates the commitment infos and contract tree heights against the general config and that
+    /// every contract class is provided. Transaction fields are checked at deserialization.
+    pub fn validate(&self) -> Result<(), OsInputError> {
+        let config = &self.general_config;
+
+        self.contract_state_commitment_info.validate(config.global_state_commitment_tree_height as usize)?;
+        self.contract_class_commitment_info.validate(config.compiled_class_hash_commitment_tree_height as usize)?;
+
+        for (address, contract) in &self.contracts {
+            let storage_height = contract.storage_commitment_tree.height;
+            let expected_height = config.contract_storage_commitment_tree_height as usize;
+            if storage_height != expected_height {
+                return Err(CommitmentInfoError::InconsistentTreeHeights(storage_height, expected_height).into());
+            }
+
+            // Undeployed contracts are reported with a zero class hash.
+            let class_hash = contract.contract_hash;
+            if class_hash != Felt252::ZERO
+                && !self.deprecated_compiled_classes.contains_key(&class_hash)
+                && !self.class_hash_to_compiled_class_hash.contains_key(&class_hash)
+            {
+                return Err(OsInputError::UnknownClassHash(*address, class_hash));
+            }
+        }
+
+        Ok(())
+    }
 }
+
 #[serde_as]
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct CommitmentInfo {
@@ -58,6 +90,38 @@ pub struct CommitmentInfo {
     pub commitment_facts: HashMap<Felt252, Vec<Felt252>>,
 }
 impl CommitmentInfo {
+    /// Checks the tree height and that the commitment facts link `previous_root` to
+    /// `updated_root`, every fact preimage being either a binary `(left, right)` or an edge
+    /// `(length, path, bottom)` node.
+    pub fn validate(&self, expected_height: usize) -> Result<(), CommitmentInfoError> {
+        if self.tree_height != expected_height {
+            return Err(CommitmentInfoError::InconsistentTreeHeights(self.tree_height, expected_height));
+        }
+
+        for (hash, preimage) in &self.commitment_facts {
+            let node_hash = match preimage.as_slice() {
+                [left, right] => felt_api2vm(pedersen_hash(&felt_vm2api(*left), &felt_vm2api(*right))),
+                [length, path, bottom] => {
+                    felt_api2vm(pedersen_hash(&felt_vm2api(*bottom), &felt_vm2api(*path))) + *length
+                }
+                _ => return Err(CommitmentInfoError::InvalidCommitmentFact(*hash)),
+            };
+            if node_hash != *hash {
+                return Err(CommitmentInfoError::InvalidCommitmentFact(*hash));
+            }
+        }
+
+        if self.previous_root != self.updated_root {
+            for root in [self.previous_root, self.updated_root] {
+                if root != Felt252::ZERO && !self.commitment_facts.contains_key(&root) {
+                    return Err(CommitmentInfoError::MissingCommitmentFact(root));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn create_from_modifications<H>(_previous_tree: MerkleTrie<H, 64>) -> Self
     where
         H: StarkHasher,
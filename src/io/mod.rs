@@ -1,20 +1,351 @@
 pub mod classes;
+pub mod da;
 pub mod input;
 pub mod output;
 
+use blockifier::abi::abi_utils::selector_from_name;
+use blockifier::transaction::constants::EXECUTE_ENTRY_POINT_NAME;
 use cairo_vm::Felt252;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
+use starknet_api::core::{calculate_contract_address, ContractAddress};
+use starknet_api::hash::StarkFelt;
+use starknet_api::transaction::{
+    DeclareTransaction, DeclareTransactionV0V1, DeployAccountTransaction, InvokeTransaction, Transaction,
+    TransactionHash,
+};
 
-use crate::utils::{Felt252HexNoPrefix, Felt252Str};
+use crate::error::OsInputError;
+use crate::utils::{felt_api2vm, Felt252HexNoPrefix, Felt252Str};
 
-// TODO(#70):
-// evaluate if we can use a more standard top level transaction type
-// - starknet_api::transaction::Transaction -> no deserialization tag information
-// - starknet::types::Transaction -> deserializes `transaction_hash` we have `hash_value`
+/// Transaction as provided to the OS in the cairo-lang `os_input`, tagged by its `type`.
+///
+/// Fields required by a transaction type and version are mandatory, so an incomplete
+/// transaction is rejected at deserialization.
+#[derive(Deserialize, Clone, Debug, Serialize)]
+#[serde(try_from = "RawInternalTransaction", into = "RawInternalTransaction")]
+pub enum InternalTransaction {
+    Declare(InternalDeclare),
+    Deploy(InternalDeploy),
+    DeployAccount(InternalDeployAccount),
+    InvokeFunction(InternalInvokeFunction),
+    L1Handler(InternalL1Handler),
+}
+
+#[derive(Clone, Debug)]
+pub enum InternalDeclare {
+    V0(InternalDeclareV0V1),
+    V1(InternalDeclareV0V1),
+    V2(InternalDeclareV2),
+}
+
+#[derive(Clone, Debug)]
+pub struct InternalDeclareV0V1 {
+    pub hash_value: Felt252,
+    pub sender_address: Felt252,
+    pub class_hash: Felt252,
+    pub max_fee: Felt252,
+    pub signature: Vec<Felt252>,
+    pub nonce: Felt252,
+}
+
+#[derive(Clone, Debug)]
+pub struct InternalDeclareV2 {
+    pub hash_value: Felt252,
+    pub sender_address: Felt252,
+    pub class_hash: Felt252,
+    pub compiled_class_hash: Felt252,
+    pub max_fee: Felt252,
+    pub signature: Vec<Felt252>,
+    pub nonce: Felt252,
+}
+
+#[derive(Clone, Debug)]
+pub struct InternalDeploy {
+    pub hash_value: Felt252,
+    pub version: Felt252,
+    pub contract_address: Felt252,
+    pub contract_address_salt: Felt252,
+    pub class_hash: Felt252,
+    pub constructor_calldata: Vec<Felt252>,
+}
+
+#[derive(Clone, Debug)]
+pub struct InternalDeployAccount {
+    pub hash_value: Felt252,
+    pub version: Felt252,
+    pub contract_address: Felt252,
+    pub contract_address_salt: Felt252,
+    pub class_hash: Felt252,
+    pub constructor_calldata: Vec<Felt252>,
+    pub max_fee: Felt252,
+    pub signature: Vec<Felt252>,
+    pub nonce: Felt252,
+}
+
+#[derive(Clone, Debug)]
+pub enum InternalInvokeFunction {
+    V0(InternalInvokeFunctionV0),
+    V1(InternalInvokeFunctionV1),
+}
+
+#[derive(Clone, Debug)]
+pub struct InternalInvokeFunctionV0 {
+    pub hash_value: Felt252,
+    pub contract_address: Felt252,
+    pub entry_point_selector: Felt252,
+    pub calldata: Vec<Felt252>,
+    pub max_fee: Felt252,
+    pub signature: Vec<Felt252>,
+}
+
+#[derive(Clone, Debug)]
+pub struct InternalInvokeFunctionV1 {
+    pub hash_value: Felt252,
+    pub sender_address: Felt252,
+    pub entry_point_selector: Felt252,
+    pub calldata: Vec<Felt252>,
+    pub max_fee: Felt252,
+    pub signature: Vec<Felt252>,
+    pub nonce: Felt252,
+}
+
+#[derive(Clone, Debug)]
+pub struct InternalL1Handler {
+    pub hash_value: Felt252,
+    pub contract_address: Felt252,
+    pub entry_point_selector: Felt252,
+    pub calldata: Vec<Felt252>,
+    pub nonce: Felt252,
+    pub paid_fee_on_l1: Option<Felt252>,
+}
+
+impl InternalTransaction {
+    /// Name of the transaction type, as expected by the OS in `tx_type`.
+    pub fn tx_type(&self) -> &'static str {
+        match self {
+            Self::Declare(_) => "DECLARE",
+            Self::Deploy(_) => "DEPLOY",
+            Self::DeployAccount(_) => "DEPLOY_ACCOUNT",
+            Self::InvokeFunction(_) => "INVOKE_FUNCTION",
+            Self::L1Handler(_) => "L1_HANDLER",
+        }
+    }
+
+    pub fn hash_value(&self) -> Felt252 {
+        match self {
+            Self::Declare(InternalDeclare::V0(tx) | InternalDeclare::V1(tx)) => tx.hash_value,
+            Self::Declare(InternalDeclare::V2(tx)) => tx.hash_value,
+            Self::Deploy(tx) => tx.hash_value,
+            Self::DeployAccount(tx) => tx.hash_value,
+            Self::InvokeFunction(InternalInvokeFunction::V0(tx)) => tx.hash_value,
+            Self::InvokeFunction(InternalInvokeFunction::V1(tx)) => tx.hash_value,
+            Self::L1Handler(tx) => tx.hash_value,
+        }
+    }
+
+    pub fn version(&self) -> Felt252 {
+        match self {
+            Self::Declare(InternalDeclare::V0(_)) => Felt252::ZERO,
+            Self::Declare(InternalDeclare::V1(_)) => Felt252::ONE,
+            Self::Declare(InternalDeclare::V2(_)) => Felt252::TWO,
+            Self::Deploy(tx) => tx.version,
+            Self::DeployAccount(tx) => tx.version,
+            Self::InvokeFunction(InternalInvokeFunction::V0(_)) => Felt252::ZERO,
+            Self::InvokeFunction(InternalInvokeFunction::V1(_)) => Felt252::ONE,
+            Self::L1Handler(_) => Felt252::ZERO,
+        }
+    }
+
+    /// Address of the account the transaction is sent from. Deprecated (v0) invokes use the
+    /// called contract instead.
+    pub fn sender_address(&self) -> Option<Felt252> {
+        match self {
+            Self::Declare(InternalDeclare::V0(tx) | InternalDeclare::V1(tx)) => Some(tx.sender_address),
+            Self::Declare(InternalDeclare::V2(tx)) => Some(tx.sender_address),
+            Self::InvokeFunction(InternalInvokeFunction::V0(tx)) => Some(tx.contract_address),
+            Self::InvokeFunction(InternalInvokeFunction::V1(tx)) => Some(tx.sender_address),
+            Self::Deploy(_) | Self::DeployAccount(_) | Self::L1Handler(_) => None,
+        }
+    }
+
+    pub fn entry_point_selector(&self) -> Option<Felt252> {
+        match self {
+            Self::InvokeFunction(InternalInvokeFunction::V0(tx)) => Some(tx.entry_point_selector),
+            Self::InvokeFunction(InternalInvokeFunction::V1(tx)) => Some(tx.entry_point_selector),
+            Self::L1Handler(tx) => Some(tx.entry_point_selector),
+            Self::Declare(_) | Self::Deploy(_) | Self::DeployAccount(_) => None,
+        }
+    }
+
+    pub fn calldata(&self) -> Option<&[Felt252]> {
+        match self {
+            Self::InvokeFunction(InternalInvokeFunction::V0(tx)) => Some(&tx.calldata),
+            Self::InvokeFunction(InternalInvokeFunction::V1(tx)) => Some(&tx.calldata),
+            Self::L1Handler(tx) => Some(&tx.calldata),
+            Self::Declare(_) | Self::Deploy(_) | Self::DeployAccount(_) => None,
+        }
+    }
+
+    pub fn max_fee(&self) -> Option<Felt252> {
+        match self {
+            Self::Declare(InternalDeclare::V0(tx) | InternalDeclare::V1(tx)) => Some(tx.max_fee),
+            Self::Declare(InternalDeclare::V2(tx)) => Some(tx.max_fee),
+            Self::DeployAccount(tx) => Some(tx.max_fee),
+            Self::InvokeFunction(InternalInvokeFunction::V0(tx)) => Some(tx.max_fee),
+            Self::InvokeFunction(InternalInvokeFunction::V1(tx)) => Some(tx.max_fee),
+            Self::Deploy(_) | Self::L1Handler(_) => None,
+        }
+    }
+
+    pub fn signature(&self) -> Option<&[Felt252]> {
+        match self {
+            Self::Declare(InternalDeclare::V0(tx) | InternalDeclare::V1(tx)) => Some(&tx.signature),
+            Self::Declare(InternalDeclare::V2(tx)) => Some(&tx.signature),
+            Self::DeployAccount(tx) => Some(&tx.signature),
+            Self::InvokeFunction(InternalInvokeFunction::V0(tx)) => Some(&tx.signature),
+            Self::InvokeFunction(InternalInvokeFunction::V1(tx)) => Some(&tx.signature),
+            Self::Deploy(_) | Self::L1Handler(_) => None,
+        }
+    }
+
+    /// Nonce of the transaction, `None` for transactions without one (deploy and v0 invoke).
+    pub fn nonce(&self) -> Option<Felt252> {
+        match self {
+            Self::Declare(InternalDeclare::V0(tx) | InternalDeclare::V1(tx)) => Some(tx.nonce),
+            Self::Declare(InternalDeclare::V2(tx)) => Some(tx.nonce),
+            Self::DeployAccount(tx) => Some(tx.nonce),
+            Self::InvokeFunction(InternalInvokeFunction::V1(tx)) => Some(tx.nonce),
+            Self::L1Handler(tx) => Some(tx.nonce),
+            Self::Deploy(_) | Self::InvokeFunction(InternalInvokeFunction::V0(_)) => None,
+        }
+    }
+
+    /// Converts a [starknet_api] transaction and its hash into the OS representation.
+    ///
+    /// Fails for transaction versions the OS does not support (V3).
+    pub fn from_api(tx: Transaction, tx_hash: TransactionHash) -> Result<Self, OsInputError> {
+        let hash_value = felt_api2vm(tx_hash.0);
+        let unsupported = |version: &str| {
+            OsInputError::InvalidTransaction(hash_value, format!("{version} transactions are not supported"))
+        };
+
+        let internal_tx = match tx {
+            Transaction::Declare(DeclareTransaction::V0(tx)) => {
+                Self::Declare(InternalDeclare::V0(InternalDeclareV0V1::from_api(tx, hash_value)))
+            }
+            Transaction::Declare(DeclareTransaction::V1(tx)) => {
+                Self::Declare(InternalDeclare::V1(InternalDeclareV0V1::from_api(tx, hash_value)))
+            }
+            Transaction::Declare(DeclareTransaction::V2(tx)) => Self::Declare(InternalDeclare::V2(InternalDeclareV2 {
+                hash_value,
+                sender_address: felt_api2vm(*tx.sender_address.0.key()),
+                class_hash: felt_api2vm(tx.class_hash.0),
+                compiled_class_hash: felt_api2vm(tx.compiled_class_hash.0),
+                max_fee: Felt252::from(tx.max_fee.0),
+                signature: felts_api2vm(&tx.signature.0),
+                nonce: felt_api2vm(tx.nonce.0),
+            })),
+            Transaction::Declare(DeclareTransaction::V3(_)) => return Err(unsupported("declare v3")),
+            Transaction::Deploy(tx) => {
+                let contract_address = calculate_contract_address(
+                    tx.contract_address_salt,
+                    tx.class_hash,
+                    &tx.constructor_calldata,
+                    ContractAddress::default(),
+                )
+                .map_err(|e| OsInputError::InvalidTransaction(hash_value, e.to_string()))?;
+
+                Self::Deploy(InternalDeploy {
+                    hash_value,
+                    version: felt_api2vm(tx.version.0),
+                    contract_address: felt_api2vm(*contract_address.0.key()),
+                    contract_address_salt: felt_api2vm(tx.contract_address_salt.0),
+                    class_hash: felt_api2vm(tx.class_hash.0),
+                    constructor_calldata: felts_api2vm(&tx.constructor_calldata.0),
+                })
+            }
+            Transaction::DeployAccount(DeployAccountTransaction::V1(tx)) => {
+                let contract_address = calculate_contract_address(
+                    tx.contract_address_salt,
+                    tx.class_hash,
+                    &tx.constructor_calldata,
+                    ContractAddress::default(),
+                )
+                .map_err(|e| OsInputError::InvalidTransaction(hash_value, e.to_string()))?;
+
+                Self::DeployAccount(InternalDeployAccount {
+                    hash_value,
+                    version: Felt252::ONE,
+                    contract_address: felt_api2vm(*contract_address.0.key()),
+                    contract_address_salt: felt_api2vm(tx.contract_address_salt.0),
+                    class_hash: felt_api2vm(tx.class_hash.0),
+                    constructor_calldata: felts_api2vm(&tx.constructor_calldata.0),
+                    max_fee: Felt252::from(tx.max_fee.0),
+                    signature: felts_api2vm(&tx.signature.0),
+                    nonce: felt_api2vm(tx.nonce.0),
+                })
+            }
+            Transaction::DeployAccount(DeployAccountTransaction::V3(_)) => {
+                return Err(unsupported("deploy account v3"));
+            }
+            Transaction::Invoke(InvokeTransaction::V0(tx)) => {
+                Self::InvokeFunction(InternalInvokeFunction::V0(InternalInvokeFunctionV0 {
+                    hash_value,
+                    contract_address: felt_api2vm(*tx.contract_address.0.key()),
+                    entry_point_selector: felt_api2vm(tx.entry_point_selector.0),
+                    calldata: felts_api2vm(&tx.calldata.0),
+                    max_fee: Felt252::from(tx.max_fee.0),
+                    signature: felts_api2vm(&tx.signature.0),
+                }))
+            }
+            Transaction::Invoke(InvokeTransaction::V1(tx)) => {
+                Self::InvokeFunction(InternalInvokeFunction::V1(InternalInvokeFunctionV1 {
+                    hash_value,
+                    sender_address: felt_api2vm(*tx.sender_address.0.key()),
+                    entry_point_selector: felt_api2vm(selector_from_name(EXECUTE_ENTRY_POINT_NAME).0),
+                    calldata: felts_api2vm(&tx.calldata.0),
+                    max_fee: Felt252::from(tx.max_fee.0),
+                    signature: felts_api2vm(&tx.signature.0),
+                    nonce: felt_api2vm(tx.nonce.0),
+                }))
+            }
+            Transaction::Invoke(InvokeTransaction::V3(_)) => return Err(unsupported("invoke v3")),
+            Transaction::L1Handler(tx) => Self::L1Handler(InternalL1Handler {
+                hash_value,
+                contract_address: felt_api2vm(*tx.contract_address.0.key()),
+                entry_point_selector: felt_api2vm(tx.entry_point_selector.0),
+                calldata: felts_api2vm(&tx.calldata.0),
+                nonce: felt_api2vm(tx.nonce.0),
+                paid_fee_on_l1: None,
+            }),
+        };
+
+        Ok(internal_tx)
+    }
+}
+
+impl InternalDeclareV0V1 {
+    fn from_api(tx: DeclareTransactionV0V1, hash_value: Felt252) -> Self {
+        Self {
+            hash_value,
+            sender_address: felt_api2vm(*tx.sender_address.0.key()),
+            class_hash: felt_api2vm(tx.class_hash.0),
+            max_fee: Felt252::from(tx.max_fee.0),
+            signature: felts_api2vm(&tx.signature.0),
+            nonce: felt_api2vm(tx.nonce.0),
+        }
+    }
+}
+
+fn felts_api2vm(felts: &[StarkFelt]) -> Vec<Felt252> {
+    felts.iter().map(|felt| felt_api2vm(*felt)).collect()
+}
+
+/// Flat cairo-lang representation of an [InternalTransaction], every field being optional.
 #[serde_as]
 #[derive(Deserialize, Clone, Debug, Serialize, Default)]
-pub struct InternalTransaction {
+pub struct RawInternalTransaction {
     #[serde_as(as = "Felt252Str")]
     pub hash_value: Felt252,
     #[serde_as(as = "Option<Felt252Str>")]
@@ -49,14 +380,190 @@ pub struct InternalTransaction {
     #[serde_as(as = "Option<Felt252Str>")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub class_hash: Option<Felt252>,
+    #[serde_as(as = "Option<Felt252Str>")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compiled_class_hash: Option<Felt252>,
     #[serde_as(as = "Option<Vec<Felt252Str>>")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub calldata: Option<Vec<Felt252>>,
+    #[serde_as(as = "Option<Felt252Str>")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub paid_on_l1: Option<bool>,
+    pub max_fee: Option<Felt252>,
+    #[serde_as(as = "Option<Felt252Str>")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paid_fee_on_l1: Option<Felt252>,
     pub r#type: String,
 }
 
+impl TryFrom<RawInternalTransaction> for InternalTransaction {
+    type Error = String;
+
+    fn try_from(raw: RawInternalTransaction) -> Result<Self, Self::Error> {
+        let hash_value = raw.hash_value;
+        let version = raw.version.unwrap_or(Felt252::ZERO);
+        let tx_type = raw.r#type.clone();
+        let required = |field: Option<_>, name: &str| {
+            field.ok_or(format!("{tx_type} transaction {hash_value} (version {version}) is missing field `{name}`"))
+        };
+
+        let tx = match raw.r#type.as_str() {
+            "DECLARE" if version == Felt252::ZERO || version == Felt252::ONE => {
+                let declare = InternalDeclareV0V1 {
+                    hash_value,
+                    sender_address: required(raw.sender_address, "sender_address")?,
+                    class_hash: required(raw.class_hash, "class_hash")?,
+                    max_fee: required(raw.max_fee, "max_fee")?,
+                    signature: required(raw.signature, "signature")?,
+                    nonce: required(raw.nonce, "nonce")?,
+                };
+                match version == Felt252::ZERO {
+                    true => Self::Declare(InternalDeclare::V0(declare)),
+                    false => Self::Declare(InternalDeclare::V1(declare)),
+                }
+            }
+            "DECLARE" if version == Felt252::TWO => Self::Declare(InternalDeclare::V2(InternalDeclareV2 {
+                hash_value,
+                sender_address: required(raw.sender_address, "sender_address")?,
+                class_hash: required(raw.class_hash, "class_hash")?,
+                compiled_class_hash: required(raw.compiled_class_hash, "compiled_class_hash")?,
+                max_fee: required(raw.max_fee, "max_fee")?,
+                signature: required(raw.signature, "signature")?,
+                nonce: required(raw.nonce, "nonce")?,
+            })),
+            "DEPLOY" => Self::Deploy(InternalDeploy {
+                hash_value,
+                version,
+                contract_address: required(raw.contract_address, "contract_address")?,
+                contract_address_salt: required(raw.contract_address_salt, "contract_address_salt")?,
+                class_hash: required(raw.contract_hash, "contract_hash")?,
+                constructor_calldata: required(raw.constructor_calldata, "constructor_calldata")?,
+            }),
+            "DEPLOY_ACCOUNT" => Self::DeployAccount(InternalDeployAccount {
+                hash_value,
+                version,
+                contract_address: required(raw.contract_address, "contract_address")?,
+                contract_address_salt: required(raw.contract_address_salt, "contract_address_salt")?,
+                class_hash: required(raw.class_hash, "class_hash")?,
+                constructor_calldata: required(raw.constructor_calldata, "constructor_calldata")?,
+                max_fee: required(raw.max_fee, "max_fee")?,
+                signature: required(raw.signature, "signature")?,
+                nonce: required(raw.nonce, "nonce")?,
+            }),
+            "INVOKE_FUNCTION" if version == Felt252::ZERO => {
+                Self::InvokeFunction(InternalInvokeFunction::V0(InternalInvokeFunctionV0 {
+                    hash_value,
+                    contract_address: required(raw.contract_address, "contract_address")?,
+                    entry_point_selector: required(raw.entry_point_selector, "entry_point_selector")?,
+                    calldata: required(raw.calldata, "calldata")?,
+                    max_fee: required(raw.max_fee, "max_fee")?,
+                    signature: required(raw.signature, "signature")?,
+                }))
+            }
+            "INVOKE_FUNCTION" if version == Felt252::ONE => {
+                Self::InvokeFunction(InternalInvokeFunction::V1(InternalInvokeFunctionV1 {
+                    hash_value,
+                    sender_address: required(raw.sender_address, "sender_address")?,
+                    entry_point_selector: required(raw.entry_point_selector, "entry_point_selector")?,
+                    calldata: required(raw.calldata, "calldata")?,
+                    max_fee: required(raw.max_fee, "max_fee")?,
+                    signature: required(raw.signature, "signature")?,
+                    nonce: required(raw.nonce, "nonce")?,
+                }))
+            }
+            "L1_HANDLER" => Self::L1Handler(InternalL1Handler {
+                hash_value,
+                contract_address: required(raw.contract_address, "contract_address")?,
+                entry_point_selector: required(raw.entry_point_selector, "entry_point_selector")?,
+                calldata: required(raw.calldata, "calldata")?,
+                nonce: required(raw.nonce, "nonce")?,
+                paid_fee_on_l1: raw.paid_fee_on_l1,
+            }),
+            "DECLARE" | "INVOKE_FUNCTION" => {
+                return Err(format!("unsupported {tx_type} transaction {hash_value} version {version}"));
+            }
+            _ => return Err(format!("unknown transaction type `{tx_type}` for transaction {hash_value}")),
+        };
+
+        Ok(tx)
+    }
+}
+
+impl From<InternalTransaction> for RawInternalTransaction {
+    fn from(tx: InternalTransaction) -> Self {
+        let raw = Self {
+            hash_value: tx.hash_value(),
+            version: Some(tx.version()),
+            r#type: tx.tx_type().to_string(),
+            ..Self::default()
+        };
+
+        match tx {
+            InternalTransaction::Declare(InternalDeclare::V0(tx) | InternalDeclare::V1(tx)) => Self {
+                sender_address: Some(tx.sender_address),
+                class_hash: Some(tx.class_hash),
+                max_fee: Some(tx.max_fee),
+                signature: Some(tx.signature),
+                nonce: Some(tx.nonce),
+                ..raw
+            },
+            InternalTransaction::Declare(InternalDeclare::V2(tx)) => Self {
+                sender_address: Some(tx.sender_address),
+                class_hash: Some(tx.class_hash),
+                compiled_class_hash: Some(tx.compiled_class_hash),
+                max_fee: Some(tx.max_fee),
+                signature: Some(tx.signature),
+                nonce: Some(tx.nonce),
+                ..raw
+            },
+            InternalTransaction::Deploy(tx) => Self {
+                contract_address: Some(tx.contract_address),
+                contract_address_salt: Some(tx.contract_address_salt),
+                contract_hash: Some(tx.class_hash),
+                constructor_calldata: Some(tx.constructor_calldata),
+                ..raw
+            },
+            InternalTransaction::DeployAccount(tx) => Self {
+                contract_address: Some(tx.contract_address),
+                contract_address_salt: Some(tx.contract_address_salt),
+                class_hash: Some(tx.class_hash),
+                constructor_calldata: Some(tx.constructor_calldata),
+                max_fee: Some(tx.max_fee),
+                signature: Some(tx.signature),
+                nonce: Some(tx.nonce),
+                ..raw
+            },
+            InternalTransaction::InvokeFunction(InternalInvokeFunction::V0(tx)) => Self {
+                contract_address: Some(tx.contract_address),
+                entry_point_selector: Some(tx.entry_point_selector),
+                entry_point_type: Some("EXTERNAL".to_string()),
+                calldata: Some(tx.calldata),
+                max_fee: Some(tx.max_fee),
+                signature: Some(tx.signature),
+                ..raw
+            },
+            InternalTransaction::InvokeFunction(InternalInvokeFunction::V1(tx)) => Self {
+                sender_address: Some(tx.sender_address),
+                entry_point_selector: Some(tx.entry_point_selector),
+                entry_point_type: Some("EXTERNAL".to_string()),
+                calldata: Some(tx.calldata),
+                max_fee: Some(tx.max_fee),
+                signature: Some(tx.signature),
+                nonce: Some(tx.nonce),
+                ..raw
+            },
+            InternalTransaction::L1Handler(tx) => Self {
+                version: None,
+                contract_address: Some(tx.contract_address),
+                entry_point_selector: Some(tx.entry_point_selector),
+                calldata: Some(tx.calldata),
+                nonce: Some(tx.nonce),
+                paid_fee_on_l1: tx.paid_fee_on_l1,
+                ..raw
+            },
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct StarknetOsOutput {
     /// The state commitment before this block.
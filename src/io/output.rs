@@ -1,10 +1,13 @@
 use cairo_vm::types::relocatable::MaybeRelocatable;
 use cairo_vm::vm::runners::builtin_runner::BuiltinRunner;
+use cairo_vm::vm::runners::cairo_pie::CairoPie;
 use cairo_vm::vm::vm_core::VirtualMachine;
 use cairo_vm::Felt252;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
 
-use crate::error::SnOsError;
-use crate::utils::felt_vm2usize;
+use crate::error::{OutputError, SnOsError};
+use crate::utils::{felt_vm2usize, keccak_felts, Felt252Str};
 
 const PREVIOUS_MERKLE_UPDATE_OFFSET: usize = 0;
 const NEW_MERKLE_UPDATE_OFFSET: usize = 1;
@@ -13,28 +16,125 @@ const BLOCK_HASH_OFFSET: usize = 3;
 const CONFIG_HASH_OFFSET: usize = 4;
 const HEADER_SIZE: usize = 5;
 
-#[derive(Debug)]
+/// Name of the output builtin segment in the [CairoPie] metadata.
+const OUTPUT_SEGMENT_NAME: &str = "output";
+
+/// Bounds of the fields packed in the second word of a contract state diff, see
+/// `serialize_contract_state_diff` in the OS.
+const NONCE_BOUND_BYTES: usize = 8;
+const N_UPDATES_BOUND_BYTES: usize = 8;
+
+#[serde_as]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct StarknetOsOutput {
     /// The state commitment before this block.
+    #[serde_as(as = "Felt252Str")]
     pub prev_state_root: Felt252,
     /// The state commitment after this block.
+    #[serde_as(as = "Felt252Str")]
     pub new_state_root: Felt252,
     /// The number (height) of this block.
+    #[serde_as(as = "Felt252Str")]
     pub block_number: Felt252,
     /// The hash of this block.
+    #[serde_as(as = "Felt252Str")]
     pub block_hash: Felt252,
     /// The Starknet chain config hash
+    #[serde_as(as = "Felt252Str")]
     pub config_hash: Felt252,
     /// List of messages sent to L1 in this block
+    #[serde_as(as = "Vec<Felt252Str>")]
     pub messages_to_l1: Vec<Felt252>,
     /// List of messages from L1 handled in this block
+    #[serde_as(as = "Vec<Felt252Str>")]
     pub messages_to_l2: Vec<Felt252>,
-    /// List of the storage updates.
+    /// List of the storage updates, without the number of updated contracts.
+    #[serde_as(as = "Vec<Felt252Str>")]
     pub state_updates: Vec<Felt252>,
-    /// List of the newly declared contract classes.
+    /// List of the newly declared contract classes, without the number of classes.
+    #[serde_as(as = "Vec<Felt252Str>")]
     pub contract_class_diff: Vec<Felt252>,
 }
 
+/// Message sent from a contract to L1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MessageToL1 {
+    /// The L2 contract sending the message.
+    pub from: Felt252,
+    /// The L1 recipient.
+    pub to: Felt252,
+    pub payload: Vec<Felt252>,
+}
+
+/// Message sent from L1 and consumed by an L1 handler.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MessageToL2 {
+    /// The L1 sender.
+    pub from: Felt252,
+    /// The L2 contract handling the message.
+    pub to: Felt252,
+    pub nonce: Felt252,
+    pub selector: Felt252,
+    pub payload: Vec<Felt252>,
+}
+
+impl MessageToL1 {
+    /// Hash of the message, as consumed on L1 by `consumeMessageFromL2`.
+    pub fn hash(&self) -> [u8; 32] {
+        keccak_felts([self.from, self.to, Felt252::from(self.payload.len())].iter().chain(&self.payload))
+    }
+}
+
+impl MessageToL2 {
+    /// Hash of the message, as registered on L1 by `sendMessageToL2`.
+    pub fn hash(&self) -> [u8; 32] {
+        let header = [self.from, self.to, self.nonce, self.selector, Felt252::from(self.payload.len())];
+        keccak_felts(header.iter().chain(&self.payload))
+    }
+}
+
+/// State diff of a single contract.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContractUpdate {
+    pub address: Felt252,
+    pub nonce: Felt252,
+    /// The new class hash, only set if the class of the contract changed.
+    pub class_hash: Option<Felt252>,
+    /// Updated storage entries as `(key, value)` pairs.
+    pub storage_updates: Vec<(Felt252, Felt252)>,
+}
+
+impl ContractUpdate {
+    /// Encodes the update as written in the OS output, packing the class update flag, the nonce and
+    /// the number of storage updates in a single felt.
+    pub fn encode(&self) -> Result<Vec<Felt252>, OutputError> {
+        let nonce = self.nonce.to_bytes_be();
+        let (nonce_high, nonce_low) = nonce.split_at(nonce.len() - NONCE_BOUND_BYTES);
+        if nonce_high.iter().any(|byte| *byte != 0) {
+            return Err(OutputError::NonceOutOfBounds(self.address, self.nonce));
+        }
+
+        let mut header = [0u8; 32];
+        let flag_index = header.len() - NONCE_BOUND_BYTES - N_UPDATES_BOUND_BYTES - 1;
+        header[flag_index] = u8::from(self.class_hash.is_some());
+        header[flag_index + 1..][..NONCE_BOUND_BYTES].copy_from_slice(nonce_low);
+        header[header.len() - N_UPDATES_BOUND_BYTES..]
+            .copy_from_slice(&(self.storage_updates.len() as u64).to_be_bytes());
+
+        let mut encoded = vec![self.address, Felt252::from_bytes_be(&header)];
+        encoded.extend(self.class_hash);
+        encoded.extend(self.storage_updates.iter().flat_map(|(key, value)| [*key, *value]));
+        Ok(encoded)
+    }
+}
+
+/// Class declared (or redeclared with a new compiled class) in the block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClassDeclaration {
+    pub class_hash: Felt252,
+    pub compiled_class_hash: Felt252,
+}
+
 impl StarknetOsOutput {
     pub fn from_run(vm: &VirtualMachine) -> Result<Self, SnOsError> {
         let builtin_end_ptrs = vm.get_return_values(8).map_err(|e| SnOsError::CatchAll(e.to_string()))?;
@@ -42,33 +142,229 @@ impl StarknetOsOutput {
             .get_builtin_runners()
             .iter()
             .find(|&elt| matches!(elt, BuiltinRunner::Output(_)))
-            .expect("Os vm should have the output builtin")
+            .ok_or_else(|| SnOsError::CatchAll("Os vm should have the output builtin".to_string()))?
             .base();
-        let size_bound_up = match builtin_end_ptrs.last().unwrap() {
-            MaybeRelocatable::Int(val) => val,
-            _ => panic!("Value should be an int"),
+        let size_bound_up = match builtin_end_ptrs.last() {
+            Some(MaybeRelocatable::Int(val)) => val,
+            _ => return Err(SnOsError::CatchAll("Output builtin end pointer should be an int".to_string())),
         };
 
         // Get is input and check that everything is an integer.
         let size = felt_vm2usize(Some(&(*size_bound_up - Felt252::from(output_base))))?;
         let raw_output = vm.get_range((output_base as isize, 0).into(), size);
-        let raw_output: Vec<Felt252> = raw_output
+        let raw_output = raw_output
             .iter()
-            .map(|x| {
-                if let MaybeRelocatable::Int(val) = x.clone().unwrap().into_owned() {
-                    val
-                } else {
-                    panic!("Output should be all integers")
-                }
+            .enumerate()
+            .map(|(offset, cell)| match cell.as_deref() {
+                Some(MaybeRelocatable::Int(val)) => Ok(*val),
+                _ => Err(OutputError::NonIntegerCell(offset)),
             })
-            .collect();
+            .collect::<Result<Vec<Felt252>, OutputError>>()?;
 
         decode_output(raw_output)
     }
+
+    /// Decodes the output written in the output builtin segment of a [CairoPie].
+    pub fn from_pie(pie: &CairoPie) -> Result<Self, SnOsError> {
+        decode_output(raw_output_from_pie(pie)?)
+    }
+
+    /// Encodes the output as written by the OS, the inverse of [decode_output].
+    pub fn encode(&self) -> Result<Vec<Felt252>, SnOsError> {
+        let n_contracts = self.decode_contract_updates()?.len();
+        let n_classes = self.decode_class_declarations()?.len();
+
+        let mut os_output =
+            vec![self.prev_state_root, self.new_state_root, self.block_number, self.block_hash, self.config_hash];
+        os_output.push(self.messages_to_l1.len().into());
+        os_output.extend(&self.messages_to_l1);
+        os_output.push(self.messages_to_l2.len().into());
+        os_output.extend(&self.messages_to_l2);
+        os_output.push(n_contracts.into());
+        os_output.extend(&self.state_updates);
+        os_output.push(n_classes.into());
+        os_output.extend(&self.contract_class_diff);
+
+        Ok(os_output)
+    }
+
+    pub fn decode_messages_to_l1(&self) -> Result<Vec<MessageToL1>, SnOsError> {
+        let mut reader = OutputReader::new(&self.messages_to_l1);
+        let mut messages = vec![];
+        while !reader.is_empty() {
+            let from = reader.read()?;
+            let to = reader.read()?;
+            let payload = reader.read_segment()?;
+            messages.push(MessageToL1 { from, to, payload });
+        }
+        Ok(messages)
+    }
+
+    pub fn decode_messages_to_l2(&self) -> Result<Vec<MessageToL2>, SnOsError> {
+        let mut reader = OutputReader::new(&self.messages_to_l2);
+        let mut messages = vec![];
+        while !reader.is_empty() {
+            let from = reader.read()?;
+            let to = reader.read()?;
+            let nonce = reader.read()?;
+            let selector = reader.read()?;
+            let payload = reader.read_segment()?;
+            messages.push(MessageToL2 { from, to, nonce, selector, payload });
+        }
+        Ok(messages)
+    }
+
+    pub fn decode_contract_updates(&self) -> Result<Vec<ContractUpdate>, SnOsError> {
+        let mut reader = OutputReader::new(&self.state_updates);
+        let mut updates = vec![];
+        while !reader.is_empty() {
+            updates.push(reader.read_contract_update()?);
+        }
+        Ok(updates)
+    }
+
+    pub fn decode_class_declarations(&self) -> Result<Vec<ClassDeclaration>, SnOsError> {
+        let mut reader = OutputReader::new(&self.contract_class_diff);
+        let mut declarations = vec![];
+        while !reader.is_empty() {
+            let class_hash = reader.read()?;
+            let compiled_class_hash = reader.read()?;
+            declarations.push(ClassDeclaration { class_hash, compiled_class_hash });
+        }
+        Ok(declarations)
+    }
 }
 
-pub fn decode_output(mut os_output: Vec<Felt252>) -> Result<StarknetOsOutput, SnOsError> {
-    let header: Vec<Felt252> = os_output.drain(..HEADER_SIZE).collect();
+/// Sequential reader over the felts of an OS output, checking every access against its bounds.
+pub(crate) struct OutputReader<'a> {
+    data: &'a [Felt252],
+    offset: usize,
+}
+
+impl<'a> OutputReader<'a> {
+    pub(crate) fn new(data: &'a [Felt252]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    pub(crate) fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.offset >= self.data.len()
+    }
+
+    pub(crate) fn read(&mut self) -> Result<Felt252, OutputError> {
+        let felt = self.data.get(self.offset).copied().ok_or(OutputError::UnexpectedEnd(self.offset))?;
+        self.offset += 1;
+        Ok(felt)
+    }
+
+    pub(crate) fn read_slice(&mut self, len: usize) -> Result<&'a [Felt252], OutputError> {
+        let slice = self
+            .offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.offset..end))
+            .ok_or(OutputError::SegmentOutOfBounds(self.offset, len))?;
+        self.offset += len;
+        Ok(slice)
+    }
+
+    /// Reads a length (or count) that must fit in the remaining output.
+    pub(crate) fn read_len(&mut self) -> Result<usize, OutputError> {
+        let offset = self.offset;
+        let len = self.read()?;
+        len.to_bigint().try_into().map_err(|_| OutputError::InvalidLength(offset, len))
+    }
+
+    /// Reads the state diff of a single contract.
+    pub(crate) fn read_contract_update(&mut self) -> Result<ContractUpdate, OutputError> {
+        let address = self.read()?;
+        // class_updated * 2**128 + nonce * 2**64 + n_updates
+        let header_offset = self.offset;
+        let header = self.read()?.to_bytes_be();
+        let (class_updated, rest) = header.split_at(header.len() - NONCE_BOUND_BYTES - N_UPDATES_BOUND_BYTES);
+        let (nonce, n_updates) = rest.split_at(NONCE_BOUND_BYTES);
+        let class_updated = Felt252::from_bytes_be_slice(class_updated);
+        if class_updated != Felt252::ZERO && class_updated != Felt252::ONE {
+            return Err(OutputError::InvalidClassUpdateFlag(header_offset, class_updated));
+        }
+
+        let class_hash = if class_updated == Felt252::ONE { Some(self.read()?) } else { None };
+        let n_updates = Felt252::from_bytes_be_slice(n_updates);
+        let updates_len = to_pairs_len(n_updates).ok_or(OutputError::InvalidLength(header_offset, n_updates))?;
+        let storage_updates =
+            self.read_slice(updates_len)?.chunks_exact(2).map(|update| (update[0], update[1])).collect();
+
+        Ok(ContractUpdate { address, nonce: Felt252::from_bytes_be_slice(nonce), class_hash, storage_updates })
+    }
+
+    /// Reads a list of pairs prefixed by the number of pairs.
+    pub(crate) fn read_pairs(&mut self) -> Result<&'a [Felt252], OutputError> {
+        let count_offset = self.offset;
+        let count = self.read()?;
+        let len = to_pairs_len(count).ok_or(OutputError::InvalidLength(count_offset, count))?;
+        self.read_slice(len)
+    }
+
+    /// Checks that the whole output was read.
+    pub(crate) fn finish(&self) -> Result<(), OutputError> {
+        match self.is_empty() {
+            true => Ok(()),
+            false => Err(OutputError::TrailingData(self.offset, self.data.len() - self.offset)),
+        }
+    }
+
+    /// Reads a length prefixed segment.
+    pub(crate) fn read_segment(&mut self) -> Result<Vec<Felt252>, OutputError> {
+        let len = self.read_len()?;
+        Ok(self.read_slice(len)?.to_vec())
+    }
+}
+
+/// Number of felts taken by `count` pairs.
+fn to_pairs_len(count: Felt252) -> Option<usize> {
+    usize::try_from(count.to_bigint()).ok()?.checked_mul(2)
+}
+
+/// Reads the felts written in the output builtin segment of a [CairoPie].
+pub fn raw_output_from_pie(pie: &CairoPie) -> Result<Vec<Felt252>, OutputError> {
+    let output_segment =
+        pie.metadata.builtin_segments.get(OUTPUT_SEGMENT_NAME).ok_or(OutputError::MissingOutputSegment)?;
+    let output_index = usize::try_from(output_segment.index).map_err(|_| OutputError::MissingOutputSegment)?;
+
+    let mut raw_output = vec![None; output_segment.size];
+    for ((segment_index, offset), value) in pie.memory.0.iter() {
+        if *segment_index == output_index && *offset < output_segment.size {
+            raw_output[*offset] = Some(value);
+        }
+    }
+    raw_output
+        .into_iter()
+        .enumerate()
+        .map(|(offset, cell)| match cell {
+            Some(MaybeRelocatable::Int(val)) => Ok(*val),
+            _ => Err(OutputError::NonIntegerCell(offset)),
+        })
+        .collect()
+}
+
+pub fn decode_output(os_output: Vec<Felt252>) -> Result<StarknetOsOutput, SnOsError> {
+    let mut reader = OutputReader::new(&os_output);
+    let header = reader.read_slice(HEADER_SIZE)?;
+    let messages_to_l1 = reader.read_segment()?;
+    let messages_to_l2 = reader.read_segment()?;
+
+    // The state diff is prefixed by the number of contracts, each entry having a variable size.
+    let n_contracts = reader.read_len()?;
+    let state_updates_start = reader.offset;
+    for _ in 0..n_contracts {
+        reader.read_contract_update()?;
+    }
+    let state_updates = os_output[state_updates_start..reader.offset].to_vec();
+
+    let contract_class_diff = reader.read_pairs()?.to_vec();
+    reader.finish()?;
 
     Ok(StarknetOsOutput {
         prev_state_root: header[PREVIOUS_MERKLE_UPDATE_OFFSET],
@@ -76,9 +372,9 @@ pub fn decode_output(mut os_output: Vec<Felt252>) -> Result<StarknetOsOutput, Sn
         block_number: header[BLOCK_NUMBER_OFFSET],
         block_hash: header[BLOCK_HASH_OFFSET],
         config_hash: header[CONFIG_HASH_OFFSET],
-        messages_to_l1: os_output.drain(1..felt_vm2usize(os_output.first())?).collect(),
-        messages_to_l2: os_output.drain(1..felt_vm2usize(os_output.first())?).collect(),
-        state_updates: os_output.drain(1..felt_vm2usize(os_output.first())?).collect(),
-        contract_class_diff: os_output.drain(1..felt_vm2usize(os_output.first())?).collect(),
+        messages_to_l1,
+        messages_to_l2,
+        state_updates,
+        contract_class_diff,
     })
 }
@@ -7,22 +7,29 @@ pub mod sharp;
 pub mod state;
 pub mod utils;
 
-use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::{fs, thread};
 
 use blockifier::block_context::BlockContext;
 use blockifier::state::state_api::StateReader;
 use blockifier::transaction::objects::TransactionExecutionInfo;
+use blockifier::transaction::transaction_execution::Transaction;
+use blockifier::transaction::transactions::ExecutableTransaction;
 use cairo_vm::cairo_run::CairoRunConfig;
 use cairo_vm::types::program::Program;
 use cairo_vm::vm::errors::vm_exception::VmException;
 use cairo_vm::vm::runners::cairo_pie::CairoPie;
 use cairo_vm::vm::runners::cairo_runner::CairoRunner;
 use cairo_vm::vm::vm_core::VirtualMachine;
+use cairo_vm::Felt252;
 use config::StarknetGeneralConfig;
 use error::SnOsError;
 use execution::deprecated_syscall_handler::DeprecatedOsSyscallHandlerWrapper;
 use execution::helper::ExecutionHelperWrapper;
+use io::input::StarknetOsInput;
 use io::output::StarknetOsOutput;
+use io::InternalTransaction;
 use state::SharedState;
 
 pub struct SnOsRunner {
@@ -34,6 +41,12 @@ pub struct SnOsRunner {
     input_path: String,
     // Block context to run against
     pub block_context: BlockContext,
+    // Maximum number of VM steps for a single run (unbounded if `None`)
+    max_steps: Option<usize>,
+    // Record the execution trace (default `true`)
+    trace_enabled: bool,
+    // Run the OS in proof mode (default `false`)
+    proof_mode: bool,
 }
 
 impl SnOsRunner {
@@ -41,26 +54,130 @@ impl SnOsRunner {
         &self,
         shared_state: SharedState<impl StateReader>,
         execution_infos: Vec<TransactionExecutionInfo>,
+    ) -> Result<CairoPie, SnOsError> {
+        self.run_os(&shared_state.block_context, None, execution_infos)
+    }
+
+    /// Runs the OS over `runs` on a pool of `n_threads` threads and returns the PIEs in input
+    /// order.
+    ///
+    /// Each run owns its input, block context and execution infos, so runs do not share any
+    /// state or file on disk.
+    pub fn run_parallel(&self, runs: Vec<PreparedOsRun>, n_threads: usize) -> Result<Vec<CairoPie>, SnOsError> {
+        let n_runs = runs.len();
+        let queue = Mutex::new(runs.into_iter().enumerate());
+        let results: Mutex<Vec<Option<Result<CairoPie, SnOsError>>>> = Mutex::new((0..n_runs).map(|_| None).collect());
+
+        thread::scope(|scope| {
+            for _ in 0..n_threads.clamp(1, n_runs.max(1)) {
+                scope.spawn(|| loop {
+                    let next = queue.lock().unwrap().next();
+                    let Some((idx, run)) = next else { break };
+
+                    let pie = self.run_os(&run.block_context, Some(run.os_input), run.execution_infos);
+                    results.lock().unwrap()[idx] = Some(pie);
+                });
+            }
+        });
+
+        results.into_inner().unwrap().into_iter().map(|pie| pie.expect("every run should be processed")).collect()
+    }
+
+    /// Executes and proves a sequence of blocks on top of `shared_state`.
+    ///
+    /// Each block is executed with blockifier on the shared
+    /// [CachedState](blockifier::state::cached_state::CachedState), committed into a
+    /// [StarknetOsInput] and run through the OS. The global state roots written by the OS must
+    /// chain, i.e. the `prev_state_root` of a block is the `new_state_root` of its parent.
+    pub fn run_blocks<S: StateReader>(
+        &self,
+        shared_state: &mut SharedState<S>,
+        blocks: Vec<OsBlock>,
+    ) -> Result<Vec<ProvenBlock>, SnOsError> {
+        let mut proven_blocks: Vec<ProvenBlock> = Vec::with_capacity(blocks.len());
+
+        for block in blocks {
+            let block_number = block.block_context.block_number;
+            if block_number != shared_state.get_block_num() {
+                return Err(SnOsError::BlockSequence(format!(
+                    "expected block {}, got block {}",
+                    shared_state.get_block_num().0,
+                    block_number.0
+                )));
+            }
+            if block.transactions.len() != block.os_transactions.len() {
+                return Err(SnOsError::BlockSequence(format!(
+                    "block {} has {} transactions but {} os transactions",
+                    block_number.0,
+                    block.transactions.len(),
+                    block.os_transactions.len()
+                )));
+            }
+
+            shared_state.block_context = block.block_context;
+
+            let mut execution_infos = Vec::with_capacity(block.transactions.len());
+            for tx in block.transactions {
+                let execution_info = tx
+                    .execute(&mut shared_state.cache, &shared_state.block_context, true, true)
+                    .map_err(|e| SnOsError::CatchAll(format!("tx execution - {e}")))?;
+                execution_infos.push(execution_info);
+            }
+
+            let block_context = shared_state.block_context.clone();
+            let os_input = shared_state.commit_block(block.os_transactions, block.block_hash)?;
+
+            let pie = self.run_os(&block_context, Some(os_input.clone()), execution_infos)?;
+            let os_output = StarknetOsOutput::from_pie(&pie)?;
+
+            if let Some(parent) = proven_blocks.last() {
+                if os_output.prev_state_root != parent.os_output.new_state_root {
+                    return Err(SnOsError::StateRootMismatch(
+                        block_number.0,
+                        os_output.prev_state_root,
+                        parent.os_output.new_state_root,
+                    ));
+                }
+            }
+
+            proven_blocks.push(ProvenBlock { os_input, os_output, pie });
+        }
+
+        Ok(proven_blocks)
+    }
+
+    /// Runs the OS, reading the input from `input_path` unless `os_input` is provided.
+    fn run_os(
+        &self,
+        block_context: &BlockContext,
+        os_input: Option<StarknetOsInput>,
+        execution_infos: Vec<TransactionExecutionInfo>,
     ) -> Result<CairoPie, SnOsError> {
         // Init CairoRunConfig
         let cairo_run_config = CairoRunConfig {
             layout: self.layout.as_str(),
             relocate_mem: true,
-            trace_enabled: true,
+            trace_enabled: self.trace_enabled,
+            proof_mode: self.proof_mode,
             ..Default::default()
         };
 
+        // Load and check the Starknet OS Input
+        let os_input = match os_input {
+            Some(os_input) => os_input,
+            None => StarknetOsInput::load(Path::new(&self.input_path))?,
+        };
+        os_input.validate()?;
+
         // Load the Starknet OS Program
-        let starknet_os = fs::read(&self.os_path).map_err(|e| SnOsError::CatchAll(format!("{e}")))?;
-        let program = Program::from_bytes(&starknet_os, Some(cairo_run_config.entrypoint))
-            .map_err(|e| SnOsError::Runner(e.into()))?;
+        let program = self.load_program(cairo_run_config.entrypoint)?;
 
         // Init cairo runner
         let mut cairo_runner = CairoRunner::new(&program, cairo_run_config.layout, cairo_run_config.proof_mode)
             .map_err(|e| SnOsError::Runner(e.into()))?;
 
         // Setup Execution Helper
-        let exec_helper = ExecutionHelperWrapper::new(execution_infos, &shared_state.block_context);
+        let exec_helper = ExecutionHelperWrapper::new(execution_infos, block_context);
 
         // Init the Cairo VM
         let mut vm = VirtualMachine::new(cairo_run_config.trace_enabled);
@@ -74,12 +191,15 @@ impl SnOsRunner {
         );
 
         // Setup Globals
-        cairo_runner.exec_scopes.insert_value("input_path", self.input_path.clone());
-        cairo_runner.exec_scopes.insert_box("block_context", Box::new(shared_state.block_context.clone()));
+        cairo_runner.exec_scopes.insert_value("os_input", os_input);
+        cairo_runner.exec_scopes.insert_box("block_context", Box::new(block_context.clone()));
         cairo_runner.exec_scopes.insert_value("execution_helper", exec_helper);
 
         // Run the Cairo VM
-        let mut sn_hint_processor = hints::SnosHintProcessor::default();
+        let mut sn_hint_processor = match self.max_steps {
+            Some(max_steps) => hints::SnosHintProcessor::with_max_steps(max_steps),
+            None => hints::SnosHintProcessor::default(),
+        };
         cairo_runner
             .run_until_pc(end, &mut vm, &mut sn_hint_processor)
             .map_err(|err| VmException::from_vm_error(&cairo_runner, &vm, err))
@@ -95,9 +215,8 @@ impl SnOsRunner {
         }
 
         // Prepare and check expected output.
-        let _os_output = StarknetOsOutput::from_run(&vm)?;
-
-        println!("{:?}", _os_output);
+        let os_output = StarknetOsOutput::from_run(&vm)?;
+        log::debug!("{os_output:?}");
 
         vm.verify_auto_deductions().map_err(|e| SnOsError::Runner(e.into()))?;
         cairo_runner.read_return_values(&mut vm).map_err(|e| SnOsError::Runner(e.into()))?;
@@ -109,6 +228,21 @@ impl SnOsRunner {
         Ok(pie)
     }
 
+    /// Hash of the compiled OS program at `os_path`, identifying the OS build being proven.
+    pub fn program_hash(&self) -> Result<Felt252, SnOsError> {
+        sharp::fact::program_hash(&self.load_program(CairoRunConfig::default().entrypoint)?)
+    }
+
+    fn load_program(&self, entrypoint: &str) -> Result<Program, SnOsError> {
+        let starknet_os = fs::read(&self.os_path).map_err(|e| SnOsError::CatchAll(format!("{e}")))?;
+        Program::from_bytes(&starknet_os, Some(entrypoint)).map_err(|e| SnOsError::Runner(e.into()))
+    }
+
+    /// Returns a [SnOsRunnerBuilder] to configure several runner options at once.
+    pub fn builder() -> SnOsRunnerBuilder {
+        SnOsRunnerBuilder::default()
+    }
+
     pub fn with_layout(layout: &str) -> Self {
         Self { layout: layout.to_string(), ..Self::default() }
     }
@@ -133,6 +267,127 @@ impl Default for SnOsRunner {
             os_path: config::DEFAULT_COMPILED_OS.to_string(),
             input_path: config::DEFAULT_INPUT_PATH.to_string(),
             block_context: StarknetGeneralConfig::default().empty_block_context(),
+            max_steps: None,
+            trace_enabled: true,
+            proof_mode: false,
         }
     }
 }
+
+/// A block to be executed and proven by [SnOsRunner::run_blocks].
+pub struct OsBlock {
+    /// Context the block is executed against, its block number must follow the shared state.
+    pub block_context: BlockContext,
+    /// Hash of the block, as written to the OS output.
+    pub block_hash: Felt252,
+    /// Transactions executed by blockifier.
+    pub transactions: Vec<Transaction>,
+    /// OS input representation of `transactions`, in the same order.
+    pub os_transactions: Vec<InternalTransaction>,
+}
+
+/// Self-contained OS run, as consumed by [SnOsRunner::run_parallel].
+pub struct PreparedOsRun {
+    pub os_input: StarknetOsInput,
+    pub block_context: BlockContext,
+    pub execution_infos: Vec<TransactionExecutionInfo>,
+}
+
+/// OS input, output and PIE produced for a single block by [SnOsRunner::run_blocks].
+pub struct ProvenBlock {
+    pub os_input: StarknetOsInput,
+    pub os_output: StarknetOsOutput,
+    pub pie: CairoPie,
+}
+
+/// Builder for [SnOsRunner], validating the combined options before handing out a runner.
+#[derive(Default)]
+pub struct SnOsRunnerBuilder {
+    layout: Option<String>,
+    os_path: Option<String>,
+    input_path: Option<String>,
+    block_context: Option<BlockContext>,
+    max_steps: Option<usize>,
+    trace_enabled: Option<bool>,
+    proof_mode: Option<bool>,
+}
+
+impl SnOsRunnerBuilder {
+    pub fn layout(mut self, layout: &str) -> Self {
+        self.layout = Some(layout.to_string());
+        self
+    }
+
+    pub fn os_path(mut self, os_path: &str) -> Self {
+        self.os_path = Some(os_path.to_string());
+        self
+    }
+
+    pub fn input_path(mut self, input_path: &str) -> Self {
+        self.input_path = Some(input_path.to_string());
+        self
+    }
+
+    pub fn block_context(mut self, block_context: BlockContext) -> Self {
+        self.block_context = Some(block_context);
+        self
+    }
+
+    pub fn max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    pub fn trace_enabled(mut self, trace_enabled: bool) -> Self {
+        self.trace_enabled = Some(trace_enabled);
+        self
+    }
+
+    pub fn proof_mode(mut self, proof_mode: bool) -> Self {
+        self.proof_mode = Some(proof_mode);
+        self
+    }
+
+    /// Validates the configuration and returns the [SnOsRunner].
+    ///
+    /// Fails if the layout is unknown or lacks one of the [config::OS_BUILTINS], if the compiled
+    /// OS program cannot be found, if the step limit is zero or if proof mode is requested: the OS
+    /// program is not compiled for proof mode.
+    pub fn build(self) -> Result<SnOsRunner, SnOsError> {
+        let defaults = SnOsRunner::default();
+
+        let layout = self.layout.unwrap_or(defaults.layout);
+        let available_builtins =
+            config::layout_builtins(&layout).ok_or(SnOsError::RunnerConfig(format!("unknown layout `{layout}`")))?;
+        let missing_builtins: Vec<&str> =
+            config::OS_BUILTINS.into_iter().filter(|builtin| !available_builtins.contains(builtin)).collect();
+        if !missing_builtins.is_empty() {
+            return Err(SnOsError::RunnerConfig(format!(
+                "layout `{layout}` is missing the os builtins {missing_builtins:?}"
+            )));
+        }
+
+        let os_path = self.os_path.unwrap_or(defaults.os_path);
+        if !Path::new(&os_path).is_file() {
+            return Err(SnOsError::RunnerConfig(format!("compiled os program not found at `{os_path}`")));
+        }
+
+        if self.max_steps == Some(0) {
+            return Err(SnOsError::RunnerConfig("max steps must be greater than zero".to_string()));
+        }
+
+        if self.proof_mode == Some(true) {
+            return Err(SnOsError::RunnerConfig("the os cannot be run in proof mode".to_string()));
+        }
+
+        Ok(SnOsRunner {
+            layout,
+            os_path,
+            input_path: self.input_path.unwrap_or(defaults.input_path),
+            block_context: self.block_context.unwrap_or(defaults.block_context),
+            max_steps: self.max_steps,
+            trace_enabled: self.trace_enabled.unwrap_or(defaults.trace_enabled),
+            proof_mode: self.proof_mode.unwrap_or(defaults.proof_mode),
+        })
+    }
+}
@@ -0,0 +1,153 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use cairo_vm::vm::runners::cairo_pie::CairoPie;
+use cairo_vm::Felt252;
+use serde_json::json;
+
+use super::fact::{compute_fact, compute_program_hash_chain, FactTopology, BOOTLOADER_VERSION};
+use super::pie::write_pie_zip;
+use crate::error::{OutputError, SnOsError};
+use crate::io::output::{decode_output, raw_output_from_pie, OutputReader, StarknetOsOutput};
+
+/// Number of felts written by the simple bootloader before the output of each task, its output
+/// size and program hash.
+const TASK_HEADER_SIZE: usize = 2;
+
+/// Output of a single task of the simple bootloader.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TaskOutput {
+    pub program_hash: Felt252,
+    pub output: Vec<Felt252>,
+}
+
+impl TaskOutput {
+    /// Output of `pie` run as a task, its program being hashed with poseidon if `use_poseidon` is
+    /// set.
+    pub fn from_pie(pie: &CairoPie, use_poseidon: bool) -> Result<Self, SnOsError> {
+        Ok(Self {
+            program_hash: compute_program_hash_chain(&pie.metadata.program, BOOTLOADER_VERSION, use_poseidon)?,
+            output: raw_output_from_pie(pie)?,
+        })
+    }
+
+    /// Fact registered for this task once the bootloader run is proven, given the topology of
+    /// its output pages.
+    pub fn fact(&self, fact_topology: &FactTopology) -> Result<[u8; 32], SnOsError> {
+        compute_fact(self.program_hash, &self.output, fact_topology)
+    }
+
+    /// Decodes the task output as the output of an OS run.
+    pub fn os_output(&self) -> Result<StarknetOsOutput, SnOsError> {
+        decode_output(self.output.clone())
+    }
+}
+
+/// Input of the simple bootloader running several OS runs, each given as a [CairoPie], in a
+/// single job.
+#[derive(Clone, Debug, Default)]
+pub struct BootloaderInput {
+    pub tasks: Vec<CairoPie>,
+    /// Hash the task programs with poseidon instead of pedersen
+    pub use_poseidon: bool,
+}
+
+impl BootloaderInput {
+    pub fn new(tasks: Vec<CairoPie>) -> Self {
+        Self { tasks, use_poseidon: false }
+    }
+
+    pub fn with_poseidon(mut self, use_poseidon: bool) -> Self {
+        self.use_poseidon = use_poseidon;
+        self
+    }
+
+    /// Adds the tasks of `other` after the tasks of `self`, keeping the program hash function of
+    /// `self`.
+    pub fn merge(mut self, other: BootloaderInput) -> Self {
+        self.tasks.extend(other.tasks);
+        self
+    }
+
+    pub fn task_outputs(&self) -> Result<Vec<TaskOutput>, SnOsError> {
+        self.tasks.iter().map(|pie| TaskOutput::from_pie(pie, self.use_poseidon)).collect()
+    }
+
+    /// Facts registered for each task once the bootloader run is proven, over the output pages
+    /// of each task.
+    pub fn task_facts(&self) -> Result<Vec<[u8; 32]>, SnOsError> {
+        self.tasks
+            .iter()
+            .zip(self.task_outputs()?)
+            .map(|(pie, task)| task.fact(&FactTopology::from_pie(pie)?))
+            .collect()
+    }
+
+    /// Output the simple bootloader writes when running the tasks.
+    pub fn output(&self) -> Result<Vec<Felt252>, SnOsError> {
+        Ok(encode_bootloader_output(&self.task_outputs()?))
+    }
+
+    /// Fact of the bootloader run, given the hash of the bootloader program. The bootloader output
+    /// is written as a single page.
+    pub fn fact(&self, bootloader_program_hash: Felt252) -> Result<[u8; 32], SnOsError> {
+        let output = self.output()?;
+        compute_fact(bootloader_program_hash, &output, &FactTopology::single_page(output.len()))
+    }
+
+    /// Writes each task as a PIE zip in `dir`, along with the `input.json` of cairo-lang's simple
+    /// bootloader referencing them. Returns the path of `input.json`.
+    pub fn write(&self, dir: &Path) -> Result<PathBuf, SnOsError> {
+        fs::create_dir_all(dir)?;
+
+        let mut tasks = Vec::with_capacity(self.tasks.len());
+        for (idx, pie) in self.tasks.iter().enumerate() {
+            let pie_path = dir.join(format!("task_{idx}.zip"));
+            write_pie_zip(pie, &pie_path)?;
+            tasks.push(json!({ "type": "CairoPiePath", "path": pie_path, "use_poseidon": self.use_poseidon }));
+        }
+
+        let input_path = dir.join("input.json");
+        let input = json!({ "tasks": tasks, "fact_topologies_path": null, "single_page": true });
+        fs::write(&input_path, serde_json::to_vec_pretty(&input)?)?;
+
+        Ok(input_path)
+    }
+}
+
+/// Encodes the output of the simple bootloader: the number of tasks followed, for each task, by
+/// its output size (header included), program hash and output.
+pub fn encode_bootloader_output(tasks: &[TaskOutput]) -> Vec<Felt252> {
+    let mut output = vec![Felt252::from(tasks.len())];
+    for task in tasks {
+        output.push(Felt252::from(task.output.len() + TASK_HEADER_SIZE));
+        output.push(task.program_hash);
+        output.extend(&task.output);
+    }
+    output
+}
+
+/// Splits the output of the simple bootloader into the output of each task, the inverse of
+/// [encode_bootloader_output].
+pub fn split_output(output: &[Felt252]) -> Result<Vec<TaskOutput>, SnOsError> {
+    let mut reader = OutputReader::new(output);
+
+    let n_tasks = reader.read_len()?;
+    let mut tasks = Vec::new();
+    for _ in 0..n_tasks {
+        let size_offset = reader.offset();
+        let size = reader.read_len()?;
+        let output_len =
+            size.checked_sub(TASK_HEADER_SIZE).ok_or(OutputError::InvalidLength(size_offset, Felt252::from(size)))?;
+        let program_hash = reader.read()?;
+        tasks.push(TaskOutput { program_hash, output: reader.read_slice(output_len)?.to_vec() });
+    }
+    reader.finish()?;
+
+    Ok(tasks)
+}
+
+/// Splits the output of the simple bootloader into the output of each OS run.
+pub fn split_os_outputs(output: &[Felt252]) -> Result<Vec<StarknetOsOutput>, SnOsError> {
+    split_output(output)?.iter().map(TaskOutput::os_output).collect()
+}
@@ -0,0 +1,235 @@
+use cairo_vm::types::program::Program;
+use cairo_vm::types::relocatable::MaybeRelocatable;
+use cairo_vm::vm::runners::builtin_runner::OUTPUT_BUILTIN_NAME;
+use cairo_vm::vm::runners::cairo_pie::{
+    BuiltinAdditionalData, CairoPie, OutputBuiltinAdditionalData, Pages, StrippedProgram,
+};
+use cairo_vm::Felt252;
+use sha3::{Digest, Keccak256};
+use starknet_crypto::{pedersen_hash, poseidon_hash_many, FieldElement};
+
+use super::pie::builtin_name;
+use crate::error::SnOsError;
+use crate::io::output::raw_output_from_pie;
+use crate::utils::keccak_felts;
+
+/// Bootloader version used by SHARP when hashing the proven programs.
+pub const BOOTLOADER_VERSION: u64 = 0;
+
+/// Hash of a compiled program as computed by the bootloader.
+pub fn program_hash(program: &Program) -> Result<Felt252, SnOsError> {
+    let stripped = program.get_stripped_program().map_err(|e| SnOsError::CatchAll(format!("program hash - {e}")))?;
+    compute_program_hash_chain(&stripped, BOOTLOADER_VERSION, false)
+}
+
+/// Hash of the program that produced a [CairoPie].
+pub fn pie_program_hash(pie: &CairoPie) -> Result<Felt252, SnOsError> {
+    compute_program_hash_chain(&pie.metadata.program, BOOTLOADER_VERSION, false)
+}
+
+/// Hash of a stripped program, following `compute_program_hash_chain` from cairo-lang.
+///
+/// The bootloader hashes `[bootloader_version, main, n_builtins, ...builtins, ...data]` with
+/// poseidon when `use_poseidon` is set, and with a pedersen hash chain prefixed by its length
+/// otherwise.
+pub fn compute_program_hash_chain(
+    program: &StrippedProgram,
+    bootloader_version: u64,
+    use_poseidon: bool,
+) -> Result<Felt252, SnOsError> {
+    let builtins =
+        program.builtins.iter().map(|builtin| Felt252::from_bytes_be_slice(builtin_name(builtin).as_bytes()));
+    let data = program
+        .data
+        .iter()
+        .map(|cell| match cell {
+            MaybeRelocatable::Int(felt) => Ok(*felt),
+            MaybeRelocatable::RelocatableValue(_) => {
+                Err(SnOsError::CatchAll("program hash - program data should only contain integers".to_string()))
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut data_chain =
+        vec![Felt252::from(bootloader_version), Felt252::from(program.main), Felt252::from(program.builtins.len())];
+    data_chain.extend(builtins);
+    data_chain.extend(data);
+
+    if use_poseidon {
+        let hash = poseidon_hash_many(&field_elements(&data_chain)?);
+        return Ok(Felt252::from_bytes_be(&hash.to_bytes_be()));
+    }
+    data_chain.insert(0, Felt252::from(data_chain.len()));
+    compute_hash_chain(&data_chain)
+}
+
+/// Pedersen hash chain `h(data[0], h(data[1], ... h(data[n-2], data[n-1])))`.
+pub fn compute_hash_chain(data: &[Felt252]) -> Result<Felt252, SnOsError> {
+    let felts = field_elements(data)?;
+    let (last, rest) = felts.split_last().ok_or_else(|| SnOsError::CatchAll("hash chain - empty data".to_string()))?;
+    let hash = rest.iter().rev().fold(*last, |acc, felt| pedersen_hash(felt, &acc));
+
+    Ok(Felt252::from_bytes_be(&hash.to_bytes_be()))
+}
+
+fn field_elements(data: &[Felt252]) -> Result<Vec<FieldElement>, SnOsError> {
+    data.iter()
+        .map(|felt| FieldElement::from_bytes_be(&felt.to_bytes_be()))
+        .collect::<Result<_, _>>()
+        .map_err(|e| SnOsError::CatchAll(format!("felt conversion - {e:?}")))
+}
+
+/// Attribute of the output builtin describing the fact topology of a program.
+pub const GPS_FACT_TOPOLOGY: &str = "gps_fact_topology";
+
+/// Structure of the merkle tree built by the GPS verifier over the output pages of a program,
+/// see `FactTopology` in cairo-lang.
+///
+/// `tree_structure` is a sequence of `(n_pages, n_nodes)` pairs: push the next `n_pages` pages to
+/// the stack, then replace its last `n_nodes` nodes by their parent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FactTopology {
+    pub tree_structure: Vec<usize>,
+    pub page_sizes: Vec<usize>,
+}
+
+impl FactTopology {
+    /// Topology of an output written as a single page.
+    pub fn single_page(output_size: usize) -> Self {
+        Self { tree_structure: vec![1, 0], page_sizes: vec![output_size] }
+    }
+
+    /// Topology described by the pages and attributes of the output builtin, following
+    /// `get_fact_topology_from_additional_data` from cairo-lang.
+    pub fn from_additional_data(
+        output_size: usize,
+        additional_data: &OutputBuiltinAdditionalData,
+    ) -> Result<Self, SnOsError> {
+        let tree_structure = match additional_data.attributes.get(GPS_FACT_TOPOLOGY) {
+            Some(tree_structure) => {
+                if tree_structure.len() % 2 != 0 || tree_structure.is_empty() || tree_structure.len() > 10 {
+                    return Err(fact_topology_error(format!("invalid tree structure {tree_structure:?}")));
+                }
+                tree_structure.clone()
+            }
+            None if additional_data.pages.is_empty() => vec![1, 0],
+            None => return Err(fact_topology_error(format!("{GPS_FACT_TOPOLOGY} is required for multiple pages"))),
+        };
+
+        Ok(Self { tree_structure, page_sizes: page_sizes(output_size, &additional_data.pages)? })
+    }
+
+    /// Topology of the output of the run contained in a [CairoPie].
+    pub fn from_pie(pie: &CairoPie) -> Result<Self, SnOsError> {
+        let output_size = raw_output_from_pie(pie)?.len();
+        match pie.additional_data.get(OUTPUT_BUILTIN_NAME) {
+            Some(BuiltinAdditionalData::Output(additional_data)) => {
+                Self::from_additional_data(output_size, additional_data)
+            }
+            None => Ok(Self::single_page(output_size)),
+            Some(_) => Err(fact_topology_error("unexpected additional data for the output builtin".to_string())),
+        }
+    }
+}
+
+/// Sizes of the output pages, page 0 being the part of the output before page 1, following
+/// `get_page_sizes_from_page_dict` from cairo-lang.
+fn page_sizes(output_size: usize, pages: &Pages) -> Result<Vec<usize>, SnOsError> {
+    let mut page_ids: Vec<&usize> = pages.keys().collect();
+    page_ids.sort();
+
+    let mut page_sizes = vec![output_size];
+    let mut expected_start = None;
+    for (idx, page_id) in page_ids.into_iter().enumerate() {
+        if *page_id != idx + 1 {
+            return Err(fact_topology_error(format!("expected page id {}, found {page_id}", idx + 1)));
+        }
+        let page = &pages[page_id];
+        match expected_start {
+            None if page.start <= output_size => page_sizes[0] = page.start,
+            Some(start) if page.start == start => {}
+            _ => return Err(fact_topology_error(format!("invalid start {} of page {page_id}", page.start))),
+        }
+        if page.size == 0 || page.size > output_size {
+            return Err(fact_topology_error(format!("invalid size {} of page {page_id}", page.size)));
+        }
+        expected_start = Some(page.start + page.size);
+        page_sizes.push(page.size);
+    }
+    if expected_start.is_some_and(|end| end != output_size) {
+        return Err(fact_topology_error("pages must cover the entire program output".to_string()));
+    }
+
+    Ok(page_sizes)
+}
+
+/// Root of the merkle tree over the output pages, following `generate_output_root` from
+/// cairo-lang.
+///
+/// Leaves are the keccak of their page; the hash of a parent node is `keccak(hash, end_offset,
+/// ...)` over its children plus one.
+pub fn generate_output_root(output: &[Felt252], topology: &FactTopology) -> Result<[u8; 32], SnOsError> {
+    if topology.page_sizes.iter().sum::<usize>() != output.len() {
+        return Err(fact_topology_error("page sizes do not match the output size".to_string()));
+    }
+
+    // (hash, end offset) of the nodes not yet attached to a parent
+    let mut node_stack: Vec<([u8; 32], usize)> = vec![];
+    let mut pages = topology.page_sizes.iter();
+    let mut offset = 0;
+    for pair in topology.tree_structure.chunks_exact(2) {
+        let (n_pages, n_nodes) = (pair[0], pair[1]);
+
+        for _ in 0..n_pages {
+            let page_size = pages.next().ok_or_else(|| fact_topology_error("not enough pages".to_string()))?;
+            let page_hash = keccak_felts(&output[offset..offset + page_size]);
+            offset += page_size;
+            node_stack.push((page_hash, offset));
+        }
+
+        if n_nodes > 0 {
+            if n_nodes > node_stack.len() {
+                return Err(fact_topology_error(format!("not enough nodes to create a parent of {n_nodes}")));
+            }
+            let children = node_stack.split_off(node_stack.len() - n_nodes);
+            let mut hasher = Keccak256::new();
+            for (hash, end_offset) in children.iter() {
+                hasher.update(hash);
+                hasher.update(Felt252::from(*end_offset).to_bytes_be());
+            }
+            let mut node_hash: [u8; 32] = hasher.finalize().into();
+            // add one, as a 256 bits big endian integer
+            for byte in node_hash.iter_mut().rev() {
+                let (sum, carry) = byte.overflowing_add(1);
+                *byte = sum;
+                if !carry {
+                    break;
+                }
+            }
+            node_stack.push((node_hash, children[n_nodes - 1].1));
+        }
+    }
+
+    match (node_stack.as_slice(), pages.next()) {
+        ([(root, _)], None) => Ok(*root),
+        _ => Err(fact_topology_error("the tree structure does not describe a single tree over all pages".to_string())),
+    }
+}
+
+fn fact_topology_error(message: String) -> SnOsError {
+    SnOsError::CatchAll(format!("fact topology - {message}"))
+}
+
+/// Fact registered by the GPS verifier for a program run, `keccak(program_hash, output_root)`
+/// where `output_root` is the root of the fact topology over the output pages.
+pub fn compute_fact(program_hash: Felt252, output: &[Felt252], topology: &FactTopology) -> Result<[u8; 32], SnOsError> {
+    let mut hasher = Keccak256::new();
+    hasher.update(program_hash.to_bytes_be());
+    hasher.update(generate_output_root(output, topology)?);
+    Ok(hasher.finalize().into())
+}
+
+/// Fact of the run contained in a [CairoPie], following `get_cairo_pie_fact_info` from cairo-lang.
+pub fn pie_fact(pie: &CairoPie) -> Result<[u8; 32], SnOsError> {
+    compute_fact(pie_program_hash(pie)?, &raw_output_from_pie(pie)?, &FactTopology::from_pie(pie)?)
+}
@@ -0,0 +1,186 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use super::{CairoJobResponse, CairoJobStatus, CairoStatusResponse};
+
+/// Failure returned by [MockSharp] instead of handling the next request.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MockFailure {
+    /// Non-200 response with the given status code and body
+    Http(u16, String),
+    /// 200 response carrying SHARP error fields instead of a job key
+    Rejected { error_type: String, error_message: String },
+}
+
+#[derive(Default)]
+struct MockState {
+    progression: Vec<CairoStatusResponse>,
+    jobs: HashMap<Uuid, VecDeque<CairoStatusResponse>>,
+    failures: VecDeque<MockFailure>,
+    n_requests: usize,
+    last_headers: HashMap<String, String>,
+}
+
+/// In-process SHARP server implementing `add_job` and `get_status`, for hermetic tests.
+///
+/// Every submitted job walks through the scripted status progression, one entry per
+/// `get_status` request, and then stays in the last state. Unknown jobs are `NOT_CREATED`.
+pub struct MockSharp {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    shutdown: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MockSharp {
+    /// Starts a server on a free local port, whose jobs are `PROCESSED` right away.
+    pub fn start() -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(MockState {
+            progression: vec![CairoJobStatus::PROCESSED.into()],
+            ..MockState::default()
+        }));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let state = state.clone();
+            let shutdown = shutdown.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if shutdown.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if let Ok(stream) = stream {
+                        // a misbehaving client only fails its own request
+                        let _ = handle_connection(stream, &state);
+                    }
+                }
+            })
+        };
+
+        Ok(Self { addr, state, shutdown, handle: Some(handle) })
+    }
+
+    /// Address to pass to [super::SharpClient::with_sharp_addr].
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Sets the status progression of the jobs submitted from now on.
+    pub fn set_progression(&self, progression: impl IntoIterator<Item = CairoStatusResponse>) {
+        let progression: Vec<_> = progression.into_iter().collect();
+        assert!(!progression.is_empty(), "status progression should not be empty");
+        self.state.lock().unwrap().progression = progression;
+    }
+
+    /// Answers the next request with `failure`. Failures are consumed in order.
+    pub fn fail_next(&self, failure: MockFailure) {
+        self.state.lock().unwrap().failures.push_back(failure);
+    }
+
+    /// Keys of the jobs submitted so far.
+    pub fn jobs(&self) -> Vec<Uuid> {
+        self.state.lock().unwrap().jobs.keys().copied().collect()
+    }
+
+    /// Value of the header `name` in the last request received, e.g. to check authentication.
+    pub fn last_header(&self, name: &str) -> Option<String> {
+        self.state.lock().unwrap().last_headers.get(&name.to_ascii_lowercase()).cloned()
+    }
+
+    /// Number of requests received so far, including failed ones.
+    pub fn n_requests(&self) -> usize {
+        self.state.lock().unwrap().n_requests
+    }
+}
+
+impl Drop for MockSharp {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // wake the listener up so it notices the shutdown
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl From<CairoJobStatus> for CairoStatusResponse {
+    fn from(status: CairoJobStatus) -> Self {
+        Self { version: 1, status, ..Self::default() }
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, state: &Mutex<MockState>) -> io::Result<()> {
+    let mut reader = BufReader::new(&stream);
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line == "\r\n" {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+    let content_length = match headers.get("content-length") {
+        Some(len) => len.parse().map_err(|_| io::ErrorKind::InvalidData)?,
+        None => 0,
+    };
+    state.lock().unwrap().last_headers = headers;
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    let (code, resp) = match serde_json::from_slice::<Value>(&body) {
+        Ok(request) => handle_request(&request, state),
+        Err(e) => (400, e.to_string()),
+    };
+
+    write!(stream, "HTTP/1.1 {code} MOCK\r\nContent-Type: application/json\r\n")?;
+    write!(stream, "Content-Length: {}\r\nConnection: close\r\n\r\n{resp}", resp.len())?;
+    stream.flush()
+}
+
+fn handle_request(request: &Value, state: &Mutex<MockState>) -> (u16, String) {
+    let mut state = state.lock().unwrap();
+    state.n_requests += 1;
+
+    match state.failures.pop_front() {
+        Some(MockFailure::Http(code, body)) => return (code, body),
+        Some(MockFailure::Rejected { error_type, error_message }) => {
+            let resp = json!({ "version": 1, "errorType": error_type, "errorMessage": error_message });
+            return (200, resp.to_string());
+        }
+        None => {}
+    }
+
+    match request["action"].as_str() {
+        Some("add_job") if request["request"]["cairo_pie"].is_string() => {
+            let job_key = Uuid::new_v4();
+            let progression = state.progression.iter().cloned().collect();
+            state.jobs.insert(job_key, progression);
+
+            let resp = CairoJobResponse { version: 1, cairo_job_key: Some(job_key), ..CairoJobResponse::default() };
+            (200, serde_json::to_string(&resp).unwrap())
+        }
+        Some("get_status") => {
+            let job = request["request"]["cairo_job_key"].as_str().and_then(|key| Uuid::parse_str(key).ok());
+            let resp = match job.and_then(|job_key| state.jobs.get_mut(&job_key)) {
+                Some(statuses) if statuses.len() > 1 => statuses.pop_front().unwrap(),
+                Some(statuses) => statuses[0].clone(),
+                None => CairoJobStatus::NOT_CREATED.into(),
+            };
+            (200, serde_json::to_string(&resp).unwrap())
+        }
+        _ => (400, format!("unsupported request {request}")),
+    }
+}
@@ -1,20 +1,28 @@
+pub mod aggregate;
+pub mod fact;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
 pub mod pie;
 
+use std::future::Future;
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 use cairo_vm::vm::runners::cairo_pie::CairoPie;
-use reqwest::blocking::Client;
-use serde::Deserialize;
-use serde_json::json;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use uuid::Uuid;
 
-use crate::error::SnOsError;
+use crate::error::{SharpError, SnOsError};
 
 #[allow(dead_code)]
 pub const DEFUALT_SHARP_URL: &str = "https://testnet.provingservice.io";
 pub const _LAMBDA_MAX_PIE_MB: u64 = 20_971_520;
 
-#[derive(Default, Clone, Debug, Deserialize, PartialEq)]
+#[derive(Default, Clone, Debug, Deserialize, Serialize, PartialEq)]
 #[allow(non_camel_case_types)]
 pub enum CairoJobStatus {
     #[default]
@@ -27,7 +35,7 @@ pub enum CairoJobStatus {
     FAILED,
 }
 
-#[derive(Default, Clone, Debug, Deserialize, PartialEq)]
+#[derive(Default, Clone, Debug, Deserialize, Serialize, PartialEq)]
 #[allow(non_camel_case_types)]
 pub enum InvalidReason {
     #[default]
@@ -43,7 +51,7 @@ pub enum InvalidReason {
     SECURITY_CHECK_FAILURE,
 }
 
-#[derive(Clone, Default, Debug, PartialEq, Deserialize)]
+#[derive(Clone, Default, Debug, PartialEq, Deserialize, Serialize)]
 pub struct CairoStatusResponse {
     #[serde(default)]
     pub version: u64,
@@ -54,7 +62,7 @@ pub struct CairoStatusResponse {
     pub invalid_reason: Option<InvalidReason>,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct CairoJobResponse {
     #[serde(default)]
     pub version: u64,
@@ -67,58 +75,202 @@ pub struct CairoJobResponse {
     pub stack_trace: Option<Vec<String>>,
 }
 
-pub struct SharpClient {
+/// Polling policy of [AsyncSharpClient::wait_for_job].
+#[derive(Clone, Debug, PartialEq)]
+pub struct PollPolicy {
+    /// Delay before the second status request
+    pub initial_interval: Duration,
+    /// Upper bound of the delay between two status requests
+    pub max_interval: Duration,
+    /// Factor applied to the delay after each status request
+    pub multiplier: f64,
+    /// Time after which polling stops with [JobOutcome::TimedOut]
+    pub timeout: Duration,
+    /// Number of consecutive transient request errors tolerated before giving up, see
+    /// [SharpError::is_transient]
+    pub max_consecutive_errors: usize,
+    /// Keep polling a `PROCESSED` job until it is `ONCHAIN`
+    pub until_onchain: bool,
+}
+
+impl Default for PollPolicy {
+    fn default() -> Self {
+        Self {
+            initial_interval: Duration::from_secs(10),
+            max_interval: Duration::from_secs(300),
+            multiplier: 2.0,
+            timeout: Duration::from_secs(4 * 60 * 60),
+            max_consecutive_errors: 3,
+            until_onchain: false,
+        }
+    }
+}
+
+impl PollPolicy {
+    /// Checks that the delay between two status requests never decreases, i.e. that the
+    /// multiplier is a finite number of at least one.
+    pub fn validate(&self) -> Result<(), SharpError> {
+        if !self.multiplier.is_finite() || self.multiplier < 1.0 {
+            return Err(SharpError::Config(format!("poll multiplier {} must be finite and >= 1", self.multiplier)));
+        }
+        Ok(())
+    }
+
+    /// Delay following `interval`, capped at `max_interval`.
+    fn next_interval(&self, interval: Duration) -> Duration {
+        Duration::try_from_secs_f64(interval.as_secs_f64() * self.multiplier)
+            .unwrap_or(self.max_interval)
+            .min(self.max_interval)
+    }
+}
+
+/// Final state of a SHARP job.
+#[derive(Clone, Debug, PartialEq)]
+pub enum JobOutcome {
+    Processed,
+    Onchain,
+    Invalid(Option<InvalidReason>),
+    Failed(Option<String>),
+    /// The job was still in the given state when the policy timeout expired
+    TimedOut(CairoJobStatus),
+}
+
+/// Asynchronous SHARP client, to be used from within a tokio runtime.
+#[derive(Clone)]
+pub struct AsyncSharpClient {
     client: Client,
     sharp_addr: String,
     pie_path: Option<PathBuf>,
 }
 
+/// Blocking SHARP client, running an [AsyncSharpClient] on its own runtime.
+///
+/// It must not be used from within a tokio runtime, use [AsyncSharpClient] there instead.
+#[derive(Clone, Default)]
+pub struct SharpClient {
+    inner: AsyncSharpClient,
+}
+
 pub enum SharpPie {
     EncodedPie(String),
     PieObject(Box<CairoPie>),
 }
 
-impl SharpClient {
-    pub fn submit_pie(&self, pie: SharpPie) -> Result<CairoJobResponse, SnOsError> {
+impl AsyncSharpClient {
+    pub async fn submit_pie(&self, pie: SharpPie) -> Result<CairoJobResponse, SnOsError> {
         let pie_enc = match pie {
             SharpPie::EncodedPie(encoded_pie) => encoded_pie,
             SharpPie::PieObject(pie_object) => match &self.pie_path {
-                Some(pp) => pie::encode_pie(*pie_object, pp.as_path())?,
-                None => pie::encode_pie_mem(*pie_object)?,
+                Some(pp) => pie::encode_pie(&pie_object, pp.as_path())?,
+                None => pie::encode_pie_mem(&pie_object)?,
             },
         };
 
         let data = json!({ "action": "add_job", "request": { "cairo_pie": pie_enc } });
 
         // CAREFUL NOT TO OVERWHELM SHARP DUE TO SHORT BLOCK TIMES
-        let resp = self
-            .client
-            .post(&self.sharp_addr)
-            .json(&data)
-            .send()
-            .map_err(|e| SnOsError::SharpRequest(format!("{e}")))?;
-
-        match resp.status() {
-            reqwest::StatusCode::OK => resp.json().map_err(|e| SnOsError::SharpRequest(format!("{e}"))),
-            _ => Err(SnOsError::SharpRequest("could not submit pie".to_string())),
+        let resp: CairoJobResponse = self.post(&data).await?;
+        if resp.cairo_job_key.is_none() {
+            return Err(SharpError::MissingJobKey.into());
         }
+
+        Ok(resp)
     }
 
-    pub fn get_status(&self, job_key: &Uuid) -> Result<CairoStatusResponse, SnOsError> {
-        let data = serde_json::json!({ "action": "get_status", "request": { "cairo_job_key": job_key } });
-
-        let resp = self
-            .client
-            .post(&self.sharp_addr)
-            .json(&data)
-            .send()
-            .map_err(|e| SnOsError::SharpRequest(format!("{e}")))?;
-
-        match resp.status() {
-            reqwest::StatusCode::OK => resp.json().map_err(|e| SnOsError::SharpRequest(format!("{e}"))),
-            _ => Err(SnOsError::SharpRequest("could not get job status".to_string())),
+    pub async fn get_status(&self, job_key: &Uuid) -> Result<CairoStatusResponse, SnOsError> {
+        let data = json!({ "action": "get_status", "request": { "cairo_job_key": job_key } });
+
+        self.post(&data).await
+    }
+
+    /// Polls the status of `job_key` until it reaches a final state.
+    pub async fn wait_for_job(&self, job_key: &Uuid, policy: &PollPolicy) -> Result<JobOutcome, SnOsError> {
+        self.wait_for_job_with(job_key, policy, |_, _| {}).await
+    }
+
+    /// Same as [AsyncSharpClient::wait_for_job], calling `on_transition(from, to)` every time the
+    /// job changes state. The first transition starts from `UNKNOWN`.
+    pub async fn wait_for_job_with(
+        &self,
+        job_key: &Uuid,
+        policy: &PollPolicy,
+        mut on_transition: impl FnMut(&CairoJobStatus, &CairoJobStatus),
+    ) -> Result<JobOutcome, SnOsError> {
+        policy.validate()?;
+
+        let start = Instant::now();
+        let mut interval = policy.initial_interval;
+        let mut status = CairoJobStatus::UNKNOWN;
+        let mut n_errors = 0;
+
+        loop {
+            match self.get_status(job_key).await {
+                Ok(resp) => {
+                    n_errors = 0;
+                    if resp.status != status {
+                        on_transition(&status, &resp.status);
+                        status = resp.status.clone();
+                    }
+
+                    match resp.status {
+                        CairoJobStatus::PROCESSED if !policy.until_onchain => return Ok(JobOutcome::Processed),
+                        CairoJobStatus::ONCHAIN => return Ok(JobOutcome::Onchain),
+                        CairoJobStatus::INVALID => return Ok(JobOutcome::Invalid(resp.invalid_reason)),
+                        CairoJobStatus::FAILED => return Ok(JobOutcome::Failed(resp.error_log)),
+                        _ => {}
+                    }
+                }
+                Err(SnOsError::Sharp(e)) if e.is_transient() && n_errors < policy.max_consecutive_errors => {
+                    n_errors += 1;
+                }
+                Err(e) => return Err(e),
+            }
+
+            let elapsed = start.elapsed();
+            if elapsed >= policy.timeout {
+                return Ok(JobOutcome::TimedOut(status));
+            }
+            tokio::time::sleep(interval.min(policy.timeout - elapsed)).await;
+            interval = policy.next_interval(interval);
+        }
+    }
+
+    /// Sends `data` to SHARP, turning both non-200 responses and 200 responses carrying error
+    /// fields into a [SharpError].
+    async fn post<T: DeserializeOwned>(&self, data: &Value) -> Result<T, SnOsError> {
+        let resp = self.client.post(&self.sharp_addr).json(data).send().await.map_err(SharpError::Network)?;
+        let status = resp.status();
+        let body = resp.text().await.map_err(SharpError::Network)?;
+
+        // error fields are the same for every action
+        let errors: CairoJobResponse = serde_json::from_str(&body).unwrap_or_default();
+        if status != reqwest::StatusCode::OK {
+            return Err(SharpError::Http {
+                status: status.as_u16(),
+                error_type: errors.error_type,
+                error_message: errors.error_message,
+                stack_trace: errors.stack_trace,
+                body,
+            }
+            .into());
         }
+        if errors.error_type.is_some() || errors.error_message.is_some() {
+            return Err(SharpError::Rejected {
+                error_type: errors.error_type,
+                error_message: errors.error_message,
+                stack_trace: errors.stack_trace,
+            }
+            .into());
+        }
+
+        serde_json::from_str(&body).map_err(|error| SharpError::Decode { body, error }.into())
     }
+
+    /// Returns a [SharpClientBuilder] to configure the endpoint and the HTTP client.
+    pub fn builder() -> SharpClientBuilder {
+        SharpClientBuilder::default()
+    }
+
     pub fn with_sharp_addr(sharp_addr: &str) -> Self {
         Self { sharp_addr: sharp_addr.to_string(), ..Self::default() }
     }
@@ -127,8 +279,194 @@ impl SharpClient {
     }
 }
 
-impl Default for SharpClient {
+impl Default for AsyncSharpClient {
     fn default() -> Self {
         Self { client: Client::new(), sharp_addr: DEFUALT_SHARP_URL.to_string(), pie_path: None }
     }
 }
+
+impl SharpClient {
+    pub fn submit_pie(&self, pie: SharpPie) -> Result<CairoJobResponse, SnOsError> {
+        block_on(self.inner.submit_pie(pie))?
+    }
+
+    pub fn get_status(&self, job_key: &Uuid) -> Result<CairoStatusResponse, SnOsError> {
+        block_on(self.inner.get_status(job_key))?
+    }
+
+    pub fn wait_for_job(&self, job_key: &Uuid, policy: &PollPolicy) -> Result<JobOutcome, SnOsError> {
+        block_on(self.inner.wait_for_job(job_key, policy))?
+    }
+
+    pub fn wait_for_job_with(
+        &self,
+        job_key: &Uuid,
+        policy: &PollPolicy,
+        on_transition: impl FnMut(&CairoJobStatus, &CairoJobStatus),
+    ) -> Result<JobOutcome, SnOsError> {
+        block_on(self.inner.wait_for_job_with(job_key, policy, on_transition))?
+    }
+
+    /// Returns a [SharpClientBuilder] to configure the endpoint and the HTTP client.
+    pub fn builder() -> SharpClientBuilder {
+        SharpClientBuilder::default()
+    }
+
+    /// Returns the underlying [AsyncSharpClient].
+    pub fn as_async(&self) -> &AsyncSharpClient {
+        &self.inner
+    }
+
+    pub fn with_sharp_addr(sharp_addr: &str) -> Self {
+        Self { inner: AsyncSharpClient::with_sharp_addr(sharp_addr) }
+    }
+    pub fn with_pie_path(pie_path: &str) -> Self {
+        Self { inner: AsyncSharpClient::with_pie_path(pie_path) }
+    }
+}
+
+impl From<AsyncSharpClient> for SharpClient {
+    fn from(inner: AsyncSharpClient) -> Self {
+        Self { inner }
+    }
+}
+
+/// TLS client certificate presented to SHARP.
+#[derive(Clone)]
+enum ClientIdentity {
+    Pkcs8Pem { cert: Vec<u8>, key: Vec<u8> },
+    Pkcs12Der { der: Vec<u8>, password: String },
+}
+
+/// Builder of [SharpClient] and [AsyncSharpClient], for SHARP endpoints requiring
+/// authentication or a specific network setup.
+#[derive(Clone, Default)]
+pub struct SharpClientBuilder {
+    sharp_addr: Option<String>,
+    pie_path: Option<PathBuf>,
+    identity: Option<ClientIdentity>,
+    root_certificate: Option<Vec<u8>>,
+    bearer_token: Option<String>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    proxy: Option<String>,
+    user_agent: Option<String>,
+}
+
+impl SharpClientBuilder {
+    pub fn sharp_addr(mut self, sharp_addr: &str) -> Self {
+        self.sharp_addr = Some(sharp_addr.to_string());
+        self
+    }
+
+    pub fn pie_path(mut self, pie_path: &str) -> Self {
+        self.pie_path = Some(PathBuf::from(pie_path));
+        self
+    }
+
+    /// Authenticates with a PEM encoded certificate chain and PKCS #8 private key.
+    pub fn client_identity_pem(mut self, cert: &[u8], key: &[u8]) -> Self {
+        self.identity = Some(ClientIdentity::Pkcs8Pem { cert: cert.to_vec(), key: key.to_vec() });
+        self
+    }
+
+    /// Authenticates with a DER encoded PKCS #12 archive.
+    pub fn client_identity_pkcs12(mut self, der: &[u8], password: &str) -> Self {
+        self.identity = Some(ClientIdentity::Pkcs12Der { der: der.to_vec(), password: password.to_string() });
+        self
+    }
+
+    /// Trusts the PEM encoded certificate, on top of the system ones.
+    pub fn root_certificate_pem(mut self, cert: &[u8]) -> Self {
+        self.root_certificate = Some(cert.to_vec());
+        self
+    }
+
+    /// Sends `Authorization: Bearer <token>` with every request.
+    pub fn bearer_token(mut self, token: &str) -> Self {
+        self.bearer_token = Some(token.to_string());
+        self
+    }
+
+    /// Timeout of a whole request, from connection to the end of the response body.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    /// Sends every request through the proxy at `proxy`, instead of the system proxy.
+    pub fn proxy(mut self, proxy: &str) -> Self {
+        self.proxy = Some(proxy.to_string());
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    /// Validates the configuration and returns the [SharpClient].
+    pub fn build(self) -> Result<SharpClient, SnOsError> {
+        Ok(SharpClient { inner: self.build_async()? })
+    }
+
+    /// Validates the configuration and returns the [AsyncSharpClient].
+    ///
+    /// Fails if the SHARP or proxy URL is invalid, if a certificate cannot be parsed or if the
+    /// bearer token is not a valid header value.
+    pub fn build_async(self) -> Result<AsyncSharpClient, SnOsError> {
+        let sharp_addr = self.sharp_addr.unwrap_or_else(|| DEFUALT_SHARP_URL.to_string());
+        reqwest::Url::parse(&sharp_addr)
+            .map_err(|e| SharpError::Config(format!("sharp address `{sharp_addr}` - {e}")))?;
+
+        let mut client = Client::builder();
+        if let Some(identity) = self.identity {
+            let identity = match identity {
+                ClientIdentity::Pkcs8Pem { cert, key } => reqwest::Identity::from_pkcs8_pem(&cert, &key),
+                ClientIdentity::Pkcs12Der { der, password } => reqwest::Identity::from_pkcs12_der(&der, &password),
+            };
+            client = client.identity(identity.map_err(|e| SharpError::Config(format!("client identity - {e}")))?);
+        }
+        if let Some(cert) = self.root_certificate {
+            let cert = reqwest::Certificate::from_pem(&cert)
+                .map_err(|e| SharpError::Config(format!("root certificate - {e}")))?;
+            client = client.add_root_certificate(cert);
+        }
+        if let Some(token) = self.bearer_token {
+            let mut auth = HeaderValue::from_str(&format!("Bearer {token}"))
+                .map_err(|e| SharpError::Config(format!("bearer token - {e}")))?;
+            auth.set_sensitive(true);
+            client = client.default_headers(HeaderMap::from_iter([(AUTHORIZATION, auth)]));
+        }
+        if let Some(timeout) = self.timeout {
+            client = client.timeout(timeout);
+        }
+        if let Some(connect_timeout) = self.connect_timeout {
+            client = client.connect_timeout(connect_timeout);
+        }
+        if let Some(proxy) = self.proxy {
+            client = client
+                .proxy(reqwest::Proxy::all(&proxy).map_err(|e| SharpError::Config(format!("proxy `{proxy}` - {e}")))?);
+        }
+        if let Some(user_agent) = self.user_agent {
+            client = client.user_agent(user_agent);
+        }
+
+        Ok(AsyncSharpClient {
+            client: client.build().map_err(|e| SharpError::Config(format!("{e}")))?,
+            sharp_addr,
+            pie_path: self.pie_path,
+        })
+    }
+}
+
+fn block_on<F: Future>(future: F) -> Result<F::Output, SnOsError> {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+
+    Ok(runtime.block_on(future))
+}
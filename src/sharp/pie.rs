@@ -1,23 +1,34 @@
 use std::fs::File;
-use std::io::{Cursor, Read, Seek, Write};
+use std::io::{BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 
 use base64::engine::general_purpose;
 use base64::Engine as _;
-use cairo_vm::vm::runners::cairo_pie::CairoPie;
+use cairo_vm::serde::deserialize_program::BuiltinName;
+use cairo_vm::types::relocatable::{MaybeRelocatable, Relocatable};
+use cairo_vm::vm::runners::cairo_pie::{CairoPie, CairoPieMemory};
+use cairo_vm::Felt252;
+use serde::de::DeserializeOwned;
 use zip::write::FileOptions;
 use zip::{ZipArchive, ZipWriter};
 
+use super::fact::pie_fact;
+use super::{InvalidReason, _LAMBDA_MAX_PIE_MB};
+use crate::config::layout_builtins;
 use crate::error::SnOsError;
 
 pub const PIE_FILES: [&str; 5] = ["metadata", "memory", "additional_data", "execution_resources", "version"];
 
-/// Writes [CairoPie] to zip file and returns the encoded base64 of the pie.
-pub fn encode_pie(pie: CairoPie, dst: &Path) -> Result<String, SnOsError> {
-    let output = File::create(dst).map_err(|e| SnOsError::PieZipping(format!("{e}")))?;
-    let zip = zip::ZipWriter::new(output);
+// Binary layout of memory.bin: 8 bytes address followed by a 32 bytes value, both little endian
+const ADDR_BYTE_LEN: usize = 8;
+const FIELD_BYTE_LEN: usize = 32;
+const N_OFFSET_BITS: usize = 47;
+const N_SEGMENT_BITS: usize = 16;
+const ADDR_BASE: u64 = 1 << (8 * ADDR_BYTE_LEN - 1);
 
-    write_to_zip(pie, zip)?;
+/// Writes [CairoPie] to zip file and returns the encoded base64 of the pie.
+pub fn encode_pie(pie: &CairoPie, dst: &Path) -> Result<String, SnOsError> {
+    write_pie_zip(pie, dst)?;
 
     let mut pie_zip = File::open(dst).map_err(|e| SnOsError::PieEncoding(format!("{e}")))?;
     let mut buffer = Vec::new();
@@ -28,7 +39,17 @@ pub fn encode_pie(pie: CairoPie, dst: &Path) -> Result<String, SnOsError> {
     Ok(general_purpose::STANDARD.encode(buffer))
 }
 
-pub fn encode_pie_mem(pie: CairoPie) -> Result<String, SnOsError> {
+/// Writes [CairoPie] to a zip file, without encoding it.
+pub fn write_pie_zip(pie: &CairoPie, dst: &Path) -> Result<(), SnOsError> {
+    let output = File::create(dst).map_err(|e| SnOsError::PieZipping(format!("{e}")))?;
+    write_to_zip(pie, zip::ZipWriter::new(BufWriter::new(output)))
+}
+
+pub fn encode_pie_mem(pie: &CairoPie) -> Result<String, SnOsError> {
+    Ok(general_purpose::STANDARD.encode(zip_pie_mem(pie)?))
+}
+
+fn zip_pie_mem(pie: &CairoPie) -> Result<Vec<u8>, SnOsError> {
     let mut data = Vec::new();
 
     {
@@ -38,25 +59,108 @@ pub fn encode_pie_mem(pie: CairoPie) -> Result<String, SnOsError> {
         write_to_zip(pie, zip)?;
     }
 
-    Ok(general_purpose::STANDARD.encode(data))
+    Ok(data)
+}
+
+/// Checks `pie` the way SHARP does before accepting a job for `layout`, returning the reason SHARP
+/// would give to reject it.
+///
+/// Builtins must be available in the layout, declared in the layout order and match the builtin
+/// segments, the fact of the run must be computable from its output pages, and the base64
+/// encoding of the zipped PIE must not exceed [_LAMBDA_MAX_PIE_MB].
+pub fn validate(pie: &CairoPie, layout: &str) -> Result<(), InvalidReason> {
+    validate_with_limit(pie, layout, _LAMBDA_MAX_PIE_MB)
+}
+
+/// [validate] with `max_encoded_size` bytes allowed for the base64 encoding of the zipped PIE.
+pub fn validate_with_limit(pie: &CairoPie, layout: &str, max_encoded_size: u64) -> Result<(), InvalidReason> {
+    let layout_builtins = layout_builtins(layout).ok_or(InvalidReason::NO_COMPATIBLE_LAYOUT)?;
+    let builtins: Vec<&str> = pie.metadata.program.builtins.iter().map(builtin_name).collect();
+    let positions = builtins
+        .iter()
+        .map(|name| layout_builtins.iter().position(|builtin| builtin == name))
+        .collect::<Option<Vec<_>>>()
+        .ok_or(InvalidReason::NO_COMPATIBLE_LAYOUT)?;
+    if !positions.windows(2).all(|pair| pair[0] < pair[1]) {
+        return Err(InvalidReason::INVALID_BUILTIN_ORDER_DECLERATION);
+    }
+    if builtins.len() != pie.metadata.builtin_segments.len()
+        || !builtins.iter().all(|name| pie.metadata.builtin_segments.contains_key(*name))
+    {
+        return Err(InvalidReason::INVALID_BUILTIN_USAGE);
+    }
+
+    pie_fact(pie).map_err(|_| InvalidReason::FAILED_TO_GENERATE_FACT)?;
+
+    // Size of the base64 encoding sent to SHARP, the zip archive is only measured
+    let mut zip_size = SizeCounter::default();
+    write_to_zip(pie, ZipWriter::new(&mut zip_size)).map_err(|_| InvalidReason::INVALID_CAIRO_PIE_FILE_FORMAT)?;
+    if zip_size.len.div_ceil(3) * 4 > max_encoded_size {
+        return Err(InvalidReason::INVALID_CAIRO_PIE_FILE_FORMAT);
+    }
+
+    Ok(())
+}
+
+/// Sink keeping track of the size of the data written to it, without storing it.
+#[derive(Debug, Default)]
+struct SizeCounter {
+    pos: u64,
+    len: u64,
+}
+
+impl Write for SizeCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.pos += buf.len() as u64;
+        self.len = self.len.max(self.pos);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for SizeCounter {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+        self.pos = new_pos.ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid seek to a negative position")
+        })?;
+        Ok(self.pos)
+    }
+}
+
+/// Name of `builtin` as used in the builtin segments, without the `_builtin` suffix.
+pub(crate) fn builtin_name(builtin: &BuiltinName) -> &'static str {
+    let name = builtin.name();
+    name.strip_suffix("_builtin").unwrap_or(name)
 }
 
 /// Write [CairoPie] to a zip Writer (either a file or a rust object).
-fn write_to_zip<W: Write + Seek>(pie: CairoPie, mut zip: ZipWriter<W>) -> Result<(), SnOsError> {
+///
+/// Every part is streamed into the archive, the PIE is never converted to an intermediate
+/// representation.
+fn write_to_zip<W: Write + Seek>(pie: &CairoPie, mut zip: ZipWriter<W>) -> Result<(), SnOsError> {
     let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated).unix_permissions(0o755);
 
-    let pie_s = serde_json::to_value(pie).map_err(|e| SnOsError::PieZipping(format!("{e}")))?;
-
     for file in PIE_FILES {
         if file == "memory" {
-            let pie_mem_bytes = hex::decode(pie_s[file].to_string().trim_matches('"'))
-                .map_err(|e| SnOsError::PieZipping(format!("{e}")))?;
-
             zip.start_file(&format!("{file}.bin"), options).map_err(|e| SnOsError::PieZipping(format!("{e}")))?;
-            zip.write_all(&pie_mem_bytes).map_err(|e| SnOsError::PieZipping(format!("{e}")))?;
+            write_memory(&pie.memory, &mut zip).map_err(|e| SnOsError::PieZipping(format!("{e}")))?;
         } else {
             zip.start_file(&format!("{file}.json"), options).map_err(|e| SnOsError::PieZipping(format!("{e}")))?;
-            zip.write_all(pie_s[file].to_string().as_bytes()).map_err(|e| SnOsError::PieZipping(format!("{e}")))?;
+            let res = match file {
+                "metadata" => serde_json::to_writer(&mut zip, &pie.metadata),
+                "additional_data" => serde_json::to_writer(&mut zip, &pie.additional_data),
+                "execution_resources" => serde_json::to_writer(&mut zip, &pie.execution_resources),
+                _ => serde_json::to_writer(&mut zip, &pie.version),
+            };
+            res.map_err(|e| SnOsError::PieZipping(format!("{file} - {e}")))?;
         };
     }
 
@@ -65,13 +169,108 @@ fn write_to_zip<W: Write + Seek>(pie: CairoPie, mut zip: ZipWriter<W>) -> Result
     Ok(())
 }
 
+/// Writes the memory cells in the layout of cairo-lang's memory.bin, the inverse of
+/// [decode_memory].
+fn write_memory<W: Write>(memory: &CairoPieMemory, writer: &mut W) -> std::io::Result<()> {
+    let mut cell = [0u8; ADDR_BYTE_LEN + FIELD_BYTE_LEN];
+
+    for ((segment_index, offset), value) in memory.0.iter() {
+        let (addr, value_bytes) = cell.split_at_mut(ADDR_BYTE_LEN);
+        addr.copy_from_slice(&(ADDR_BASE | encode_address(*segment_index, *offset)).to_le_bytes());
+        match value {
+            MaybeRelocatable::Int(felt) => value_bytes.copy_from_slice(&felt.to_bytes_le()),
+            MaybeRelocatable::RelocatableValue(relocatable) => {
+                let addr = encode_address(relocatable.segment_index as usize, relocatable.offset);
+                value_bytes.fill(0);
+                value_bytes[..ADDR_BYTE_LEN].copy_from_slice(&addr.to_le_bytes());
+                // the highest bit flags relocatable values
+                value_bytes[FIELD_BYTE_LEN - 1] = 0x80;
+            }
+        }
+        writer.write_all(&cell)?;
+    }
+
+    Ok(())
+}
+
+fn encode_address(segment_index: usize, offset: usize) -> u64 {
+    ((segment_index as u64) << N_OFFSET_BITS) + offset as u64
+}
+
 /// Convert the base64 encoding of the pie to an unzipped folder.
 pub fn decode_base64_to_unzipped(pie_str: &str, dst: &str) -> Result<(), SnOsError> {
     let buffer =
         general_purpose::STANDARD.decode(pie_str.as_bytes()).map_err(|e| SnOsError::PieZipping(format!("{e}")))?;
     ZipArchive::new(Cursor::new(&buffer))
-        .unwrap()
-        .extract(&PathBuf::from(dst))
+        .and_then(|mut zip| zip.extract(PathBuf::from(dst)))
         .map_err(|e| SnOsError::PieZipping(format!("{e}")))?;
     Ok(())
 }
+
+/// Decodes the base64 encoding of a zipped [CairoPie], as sent to SHARP.
+pub fn decode_pie(pie_str: &str) -> Result<CairoPie, SnOsError> {
+    let buffer =
+        general_purpose::STANDARD.decode(pie_str.as_bytes()).map_err(|e| SnOsError::PieParsing(format!("{e}")))?;
+    read_pie(Cursor::new(buffer))
+}
+
+/// Reads a [CairoPie] from a zip file, such as the ones written by [encode_pie].
+pub fn read_pie_zip(path: &Path) -> Result<CairoPie, SnOsError> {
+    let file = File::open(path).map_err(|e| SnOsError::PieParsing(format!("{e}")))?;
+    read_pie(file)
+}
+
+fn read_pie<R: Read + Seek>(reader: R) -> Result<CairoPie, SnOsError> {
+    let mut zip = ZipArchive::new(reader).map_err(|e| SnOsError::PieParsing(format!("{e}")))?;
+
+    let mut memory = Vec::new();
+    zip.by_name("memory.bin")
+        .map_err(|e| SnOsError::PieParsing(format!("memory.bin - {e}")))?
+        .read_to_end(&mut memory)
+        .map_err(|e| SnOsError::PieParsing(format!("memory.bin - {e}")))?;
+
+    Ok(CairoPie {
+        metadata: read_zip_json(&mut zip, "metadata.json")?,
+        memory: decode_memory(&memory)?,
+        execution_resources: read_zip_json(&mut zip, "execution_resources.json")?,
+        additional_data: read_zip_json(&mut zip, "additional_data.json")?,
+        version: read_zip_json(&mut zip, "version.json")?,
+    })
+}
+
+fn read_zip_json<R: Read + Seek, T: DeserializeOwned>(zip: &mut ZipArchive<R>, name: &str) -> Result<T, SnOsError> {
+    let file = zip.by_name(name).map_err(|e| SnOsError::PieParsing(format!("{name} - {e}")))?;
+    serde_json::from_reader(BufReader::new(file)).map_err(|e| SnOsError::PieParsing(format!("{name} - {e}")))
+}
+
+/// Decodes the memory cells of memory.bin, the inverse of the PIE memory serialization.
+fn decode_memory(bytes: &[u8]) -> Result<CairoPieMemory, SnOsError> {
+    if bytes.len() % (ADDR_BYTE_LEN + FIELD_BYTE_LEN) != 0 {
+        return Err(SnOsError::PieParsing(format!("memory.bin - invalid length {}", bytes.len())));
+    }
+
+    let cells = bytes
+        .chunks_exact(ADDR_BYTE_LEN + FIELD_BYTE_LEN)
+        .map(|cell| {
+            let (addr, value) = cell.split_at(ADDR_BYTE_LEN);
+            // the highest bit flags relocatable values
+            let value = if value[FIELD_BYTE_LEN - 1] & 0x80 != 0 {
+                let (segment_index, offset) = decode_address(&value[..ADDR_BYTE_LEN]);
+                MaybeRelocatable::RelocatableValue(Relocatable::from((segment_index as isize, offset)))
+            } else {
+                MaybeRelocatable::Int(Felt252::from_bytes_le_slice(value))
+            };
+            (decode_address(addr), value)
+        })
+        .collect();
+
+    Ok(CairoPieMemory(cells))
+}
+
+fn decode_address(bytes: &[u8]) -> (usize, usize) {
+    let addr = u64::from_le_bytes(bytes.try_into().expect("addresses are 8 bytes long"));
+    let segment_index = (addr >> N_OFFSET_BITS) & ((1 << N_SEGMENT_BITS) - 1);
+    let offset = addr & ((1 << N_OFFSET_BITS) - 1);
+
+    (segment_index as usize, offset as usize)
+}
@@ -11,16 +11,18 @@ use blockifier::state::state_api::{State, StateReader};
 use cairo_vm::Felt252;
 use indexmap::{IndexMap, IndexSet};
 use starknet_api::block::BlockNumber;
-use starknet_api::core::{ClassHash, ContractAddress, PatriciaKey};
-use starknet_api::deprecated_contract_class::ContractClass as DeprecatedContractClass;
+use starknet_api::core::{ClassHash, ContractAddress, Nonce, PatriciaKey};
 use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::StorageKey;
 use starknet_api::{patricia_key, stark_felt};
 use storage::TrieStorage;
-use trie::{MerkleTrie, PedersenHash};
+use trie::{MerkleTrie, PedersenHash, StarkHasher};
 
-use crate::config::DEFAULT_STORAGE_TREE_HEIGHT;
-use crate::io::input::CommitmentInfo;
-use crate::utils::{calculate_contract_state_hash, deprecated_class_vm2api, felt_to_bits_api};
+use crate::config::{StarknetGeneralConfig, DEFAULT_STORAGE_TREE_HEIGHT};
+use crate::error::SnOsError;
+use crate::io::input::{CommitmentInfo, ContractState, StarknetOsInput, StorageCommitment};
+use crate::io::InternalTransaction;
+use crate::utils::{calculate_contract_state_hash, deprecated_class_vm2api, felt_api2vm, felt_to_bits_api};
 
 pub struct SharedState<S: StateReader> {
     pub cache: CachedState<S>,
@@ -28,6 +30,10 @@ pub struct SharedState<S: StateReader> {
     pub commitment_storage: TrieStorage,
     pub contract_storage: TrieStorage,
     pub class_storage: TrieStorage,
+    // Class hash and nonce of each contract as of the last commitment
+    committed_contracts: HashMap<ContractAddress, (ClassHash, Nonce)>,
+    // Cumulative state diff of the cache as of the last commitment
+    committed_diff: CommitmentStateDiff,
 }
 
 impl<S: StateReader> SharedState<S> {
@@ -38,6 +44,13 @@ impl<S: StateReader> SharedState<S> {
             commitment_storage: TrieStorage::default(),
             contract_storage: TrieStorage::default(),
             class_storage: TrieStorage::default(),
+            committed_contracts: HashMap::new(),
+            committed_diff: CommitmentStateDiff {
+                address_to_class_hash: IndexMap::new(),
+                address_to_nonce: IndexMap::new(),
+                storage_updates: IndexMap::new(),
+                class_hash_to_compiled_class_hash: IndexMap::new(),
+            },
         }
     }
     pub fn get_block_num(&self) -> BlockNumber {
@@ -60,29 +73,78 @@ impl<S: StateReader> SharedState<S> {
         self.contract_storage.root_map.get(addr.0.key())
     }
 
+    /// State diff of the current block, i.e. the changes of the cache since the last commitment.
+    ///
+    /// The cache accumulates the writes of every block, so the entries already committed with
+    /// the same value are left out. Storage cells written back to their value from before the
+    /// first block no longer appear in the cache diff, their value is read from the cache.
+    pub fn block_diff(&mut self) -> CommitmentStateDiff {
+        let diff = self.cache.to_state_diff();
+        let committed = &self.committed_diff;
+
+        let mut storage_updates: IndexMap<ContractAddress, IndexMap<StorageKey, StarkFelt>> = IndexMap::new();
+        for (addr, updates) in diff.storage_updates.iter() {
+            let committed_updates = committed.storage_updates.get(addr);
+            for (key, value) in updates {
+                if committed_updates.and_then(|committed_updates| committed_updates.get(key)) != Some(value) {
+                    storage_updates.entry(*addr).or_default().insert(*key, *value);
+                }
+            }
+        }
+        for (addr, updates) in committed.storage_updates.iter() {
+            for key in updates.keys() {
+                if !diff.storage_updates.get(addr).is_some_and(|updates| updates.contains_key(key)) {
+                    let value = self.cache.get_storage_at(*addr, *key).unwrap();
+                    storage_updates.entry(*addr).or_default().insert(*key, value);
+                }
+            }
+        }
+
+        CommitmentStateDiff {
+            address_to_class_hash: diff
+                .address_to_class_hash
+                .into_iter()
+                .filter(|(addr, class_hash)| committed.address_to_class_hash.get(addr) != Some(class_hash))
+                .collect(),
+            address_to_nonce: diff
+                .address_to_nonce
+                .into_iter()
+                .filter(|(addr, nonce)| committed.address_to_nonce.get(addr) != Some(nonce))
+                .collect(),
+            storage_updates,
+            class_hash_to_compiled_class_hash: diff
+                .class_hash_to_compiled_class_hash
+                .into_iter()
+                .filter(|(class_hash, compiled_class_hash)| {
+                    committed.class_hash_to_compiled_class_hash.get(class_hash) != Some(compiled_class_hash)
+                })
+                .collect(),
+        }
+    }
+
     /// Class Commitment Trie
     pub fn apply_class_state(&mut self) -> CommitmentInfo {
-        let diff = self.cache.to_state_diff();
+        let diff = self.block_diff();
 
         let mut class_hash_trie: MerkleTrie<PedersenHash, DEFAULT_STORAGE_TREE_HEIGHT> =
-            match self.get_block_num().prev() {
-                Some(block_num) => MerkleTrie::new(self.get_class_hash_root(block_num).1),
-                None => MerkleTrie::empty(),
-            };
+            open_trie(self.get_block_num().prev().map(|block_num| self.get_class_hash_root(block_num)));
 
         for (class_hash, compiled_class_hash) in diff.class_hash_to_compiled_class_hash.clone() {
-            class_hash_trie.set(&self.contract_storage, felt_to_bits_api(class_hash.0), compiled_class_hash.0).unwrap();
+            class_hash_trie.set(&self.class_storage, felt_to_bits_api(class_hash.0), compiled_class_hash.0).unwrap();
         }
 
         let block_num = self.get_block_num();
         let previous_root = self.get_class_hash_root(block_num.prev().unwrap_or(BlockNumber(0)));
+        // collected before the commitment overwrites the leaves of the previous tree
+        let mut facts = commitment_facts(&self.class_storage, previous_root);
         let updated_root = self.class_storage.commit_and_persist(class_hash_trie, stark_felt!(block_num.0));
+        facts.extend(commitment_facts(&self.class_storage, updated_root));
 
         CommitmentInfo {
             previous_root: Felt252::from_bytes_be_slice(previous_root.0.bytes()),
             updated_root: Felt252::from_bytes_be_slice(updated_root.0.bytes()),
             tree_height: DEFAULT_STORAGE_TREE_HEIGHT,
-            commitment_facts: HashMap::new(),
+            commitment_facts: facts,
         }
     }
 
@@ -90,11 +152,8 @@ impl<S: StateReader> SharedState<S> {
     pub fn apply_state(&mut self) -> CommitmentInfo {
         let (accessed_addrs, diff) = self.apply_diff();
 
-        let mut storage_trie: MerkleTrie<PedersenHash, DEFAULT_STORAGE_TREE_HEIGHT> = match self.get_block_num().prev()
-        {
-            Some(block_num) => MerkleTrie::new(self.get_storage_root(block_num).1),
-            None => MerkleTrie::empty(),
-        };
+        let mut storage_trie: MerkleTrie<PedersenHash, DEFAULT_STORAGE_TREE_HEIGHT> =
+            open_trie(self.get_block_num().prev().map(|block_num| self.get_storage_root(block_num)));
 
         for addr in accessed_addrs {
             let nonce = match diff.address_to_nonce.get(&addr) {
@@ -110,45 +169,35 @@ impl<S: StateReader> SharedState<S> {
                 None => self.cache.get_class_hash_at(addr).unwrap(),
             };
 
+            self.committed_contracts.insert(addr, (class_hash, nonce));
             let contract_commitment = calculate_contract_state_hash(class_hash, root, nonce);
 
             storage_trie.set(&self.commitment_storage, felt_to_bits_api(*addr.0.key()), contract_commitment).unwrap();
         }
         let block_num = self.get_block_num();
         let previous_root = self.get_storage_root(block_num.prev().unwrap_or(BlockNumber(0)));
+        let mut facts = commitment_facts(&self.commitment_storage, previous_root);
         let updated_root = self.commitment_storage.commit_and_persist(storage_trie, stark_felt!(block_num.0));
+        facts.extend(commitment_facts(&self.commitment_storage, updated_root));
+        // the next block only commits the changes made after this point
+        self.committed_diff = self.cache.to_state_diff();
         self.increment_block();
 
         CommitmentInfo {
             previous_root: Felt252::from_bytes_be_slice(previous_root.0.bytes()),
             updated_root: Felt252::from_bytes_be_slice(updated_root.0.bytes()),
             tree_height: DEFAULT_STORAGE_TREE_HEIGHT,
-            commitment_facts: HashMap::new(),
+            commitment_facts: facts,
         }
     }
 
     pub fn apply_diff(&mut self) -> (IndexSet<ContractAddress>, CommitmentStateDiff) {
-        let diff = self.cache.to_state_diff();
-        let mut accessed_addrs = IndexSet::new();
-
-        let mut deprecated_declared_classes: IndexMap<ClassHash, DeprecatedContractClass> = IndexMap::new();
-
-        for (addr, class_hash) in diff.address_to_class_hash.clone().into_iter() {
-            match self.cache.get_compiled_contract_class(class_hash).unwrap() {
-                ContractClass::V0(class_inner) => {
-                    deprecated_declared_classes.insert(class_hash, deprecated_class_vm2api(&class_inner));
-                }
-                ContractClass::V1(_) => todo!("handle v1"),
-            }
-            accessed_addrs.insert(addr);
-        }
+        let diff = self.block_diff();
+        let mut accessed_addrs: IndexSet<ContractAddress> = diff.address_to_class_hash.keys().cloned().collect();
 
         for (addr, updates) in diff.storage_updates.clone() {
             let mut contract_trie: MerkleTrie<PedersenHash, DEFAULT_STORAGE_TREE_HEIGHT> =
-                match self.get_contract_root(addr) {
-                    Some((_, idx)) => MerkleTrie::new(*idx),
-                    None => MerkleTrie::empty(),
-                };
+                open_trie(self.get_contract_root(addr).copied());
 
             for (storage_key, storage_val) in updates.clone() {
                 contract_trie.set(&self.contract_storage, felt_to_bits_api(*storage_key.0.key()), storage_val).unwrap();
@@ -164,4 +213,130 @@ impl<S: StateReader> SharedState<S> {
 
         (accessed_addrs, diff)
     }
+
+    /// Commits the state changes of the current block and builds the matching [StarknetOsInput].
+    ///
+    /// Contracts are described by their state before the block: the class hash and nonce last
+    /// committed, or read from the underlying state if the contract was not committed yet, and the
+    /// storage root captured before the commitment. Moves the shared state to the next block.
+    pub fn commit_block(
+        &mut self,
+        transactions: Vec<InternalTransaction>,
+        block_hash: Felt252,
+    ) -> Result<StarknetOsInput, SnOsError> {
+        let diff = self.block_diff();
+        let general_config = StarknetGeneralConfig::try_from(self.block_context.clone())?;
+
+        let accessed_addrs: IndexSet<ContractAddress> = diff
+            .address_to_class_hash
+            .keys()
+            .chain(diff.address_to_nonce.keys())
+            .chain(diff.storage_updates.keys())
+            .cloned()
+            .collect();
+
+        let mut contracts = HashMap::new();
+        let mut used_classes = IndexSet::new();
+        for addr in accessed_addrs {
+            let (class_hash, nonce) = match self.committed_contracts.get(&addr) {
+                Some(committed) => *committed,
+                None => (
+                    self.cache
+                        .state
+                        .get_class_hash_at(addr)
+                        .map_err(|e| SnOsError::CatchAll(format!("state - {e}")))?,
+                    self.cache.state.get_nonce_at(addr).map_err(|e| SnOsError::CatchAll(format!("state - {e}")))?,
+                ),
+            };
+            let root = self.get_contract_root(addr).map(|(root, _idx)| *root).unwrap_or(StarkFelt::ZERO);
+
+            used_classes.insert(class_hash);
+            if let Some(new_class_hash) = diff.address_to_class_hash.get(&addr) {
+                used_classes.insert(*new_class_hash);
+            }
+
+            contracts.insert(
+                felt_api2vm(*addr.0.key()),
+                ContractState {
+                    contract_hash: felt_api2vm(class_hash.0),
+                    storage_commitment_tree: StorageCommitment {
+                        root: felt_api2vm(root),
+                        height: DEFAULT_STORAGE_TREE_HEIGHT,
+                    },
+                    nonce: felt_api2vm(nonce.0),
+                },
+            );
+        }
+        used_classes.extend(diff.class_hash_to_compiled_class_hash.keys());
+
+        let mut class_hash_to_compiled_class_hash: HashMap<Felt252, Felt252> = diff
+            .class_hash_to_compiled_class_hash
+            .iter()
+            .map(|(class_hash, compiled_class_hash)| (felt_api2vm(class_hash.0), felt_api2vm(compiled_class_hash.0)))
+            .collect();
+
+        // Cairo 1 classes are referenced by compiled class hash, Cairo 0 classes are provided in full.
+        let mut deprecated_compiled_classes = HashMap::new();
+        let mut compiled_classes = HashMap::new();
+        for class_hash in used_classes {
+            if class_hash == ClassHash::default() {
+                continue;
+            }
+            match self.cache.get_compiled_contract_class(class_hash) {
+                Ok(ContractClass::V0(class_inner)) => {
+                    deprecated_compiled_classes
+                        .insert(felt_api2vm(class_hash.0), deprecated_class_vm2api(&class_inner));
+                }
+                Ok(ContractClass::V1(_)) => {
+                    let compiled_class_hash = self
+                        .cache
+                        .get_compiled_class_hash(class_hash)
+                        .map_err(|e| SnOsError::CatchAll(format!("state - {e}")))?;
+                    compiled_classes.insert(felt_api2vm(compiled_class_hash.0), felt_api2vm(class_hash.0));
+                    class_hash_to_compiled_class_hash
+                        .insert(felt_api2vm(class_hash.0), felt_api2vm(compiled_class_hash.0));
+                }
+                Err(e) => return Err(SnOsError::CatchAll(format!("state - {e}"))),
+            }
+        }
+
+        let contract_class_commitment_info = self.apply_class_state();
+        let contract_state_commitment_info = self.apply_state();
+
+        Ok(StarknetOsInput {
+            contract_state_commitment_info,
+            contract_class_commitment_info,
+            deprecated_compiled_classes,
+            compiled_classes,
+            contracts,
+            class_hash_to_compiled_class_hash,
+            general_config,
+            transactions,
+            block_hash,
+        })
+    }
+}
+
+/// Opens the trie committed with `root`, or an empty trie if nothing was committed yet.
+fn open_trie<H: StarkHasher, const HEIGHT: usize>(root: Option<(StarkFelt, u64)>) -> MerkleTrie<H, HEIGHT> {
+    match root {
+        Some((hash, idx)) if hash != StarkFelt::ZERO => MerkleTrie::new(idx),
+        _ => MerkleTrie::empty(),
+    }
+}
+
+/// Commitment facts of the tree rooted at `root`, as expected in a [CommitmentInfo].
+fn commitment_facts(storage: &TrieStorage, (root, idx): (StarkFelt, u64)) -> HashMap<Felt252, Vec<Felt252>> {
+    if root == StarkFelt::ZERO {
+        return HashMap::new();
+    }
+
+    let facts = storage.commitment_facts::<PedersenHash>(idx).unwrap();
+    facts
+        .into_iter()
+        .map(|(hash, preimage)| {
+            let preimage = preimage.iter().map(|felt| Felt252::from_bytes_be_slice(felt.bytes())).collect();
+            (Felt252::from_bytes_be_slice(hash.bytes()), preimage)
+        })
+        .collect()
 }
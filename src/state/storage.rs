@@ -4,6 +4,7 @@ use anyhow::Context;
 use bitvec::prelude::{BitSlice, BitVec, Msb0};
 use starknet_api::hash::StarkFelt;
 
+use super::node::TrieNode;
 use super::trie::{MerkleTrie, StarkHasher};
 use crate::utils::felt_from_bits_api;
 
@@ -77,6 +78,12 @@ impl TrieStorage {
 
         let update = tree.commit(self).unwrap();
 
+        // An empty trie has no root node to persist
+        if update.root == StarkFelt::ZERO {
+            self.root_map.insert(root_key, (StarkFelt::ZERO, 0));
+            return (StarkFelt::ZERO, 0);
+        }
+
         let mut indices = HashMap::new();
         let mut idx = self.nodes.len();
         for hash in update.nodes.keys() {
@@ -120,4 +127,68 @@ impl TrieStorage {
 
         (update.root, index)
     }
+    /// Preimages of the nodes of the tree rooted at `root`, keyed by node hash, in the format of
+    /// the commitment facts of the OS input: `[left, right]` for binary nodes and
+    /// `[length, path, bottom]` for edge nodes.
+    ///
+    /// Only the latest value of each leaf is stored, so the bottom nodes of an older tree whose
+    /// leaves were updated since are left out.
+    pub fn commitment_facts<H: StarkHasher>(&self, root: u64) -> anyhow::Result<HashMap<StarkFelt, Vec<StarkFelt>>> {
+        let mut facts = HashMap::new();
+        self.collect_facts::<H>(root, BitVec::new(), &mut facts)?;
+        Ok(facts)
+    }
+
+    fn collect_facts<H: StarkHasher>(
+        &self,
+        index: u64,
+        path: BitVec<u8, Msb0>,
+        facts: &mut HashMap<StarkFelt, Vec<StarkFelt>>,
+    ) -> anyhow::Result<()> {
+        let (hash, node) = self.nodes.get(&index).context("Node missing from storage")?;
+
+        let child_path = |suffix: &BitSlice<u8, Msb0>| {
+            let mut child_path = path.clone();
+            child_path.extend_from_bitslice(suffix);
+            child_path
+        };
+        let leaf = |suffix: &BitSlice<u8, Msb0>| -> anyhow::Result<StarkFelt> {
+            self.leaf(&child_path(suffix))?.context("Leaf value missing from storage")
+        };
+        let child_hash =
+            |child: u64| -> anyhow::Result<StarkFelt> { self.hash(child)?.context("Child hash missing from storage") };
+        let left = BitVec::<u8, Msb0>::repeat(false, 1);
+        let right = BitVec::<u8, Msb0>::repeat(true, 1);
+
+        let trie_node = match node {
+            StoredNode::Binary { left: left_idx, right: right_idx } => {
+                self.collect_facts::<H>(*left_idx, child_path(&left), facts)?;
+                self.collect_facts::<H>(*right_idx, child_path(&right), facts)?;
+                TrieNode::Binary { left: child_hash(*left_idx)?, right: child_hash(*right_idx)? }
+            }
+            StoredNode::Edge { child, path: edge_path } => {
+                self.collect_facts::<H>(*child, child_path(edge_path), facts)?;
+                TrieNode::Edge { child: child_hash(*child)?, path: edge_path.clone() }
+            }
+            StoredNode::LeafBinary => TrieNode::Binary { left: leaf(&left)?, right: leaf(&right)? },
+            StoredNode::LeafEdge { path: edge_path } => {
+                TrieNode::Edge { child: leaf(edge_path)?, path: edge_path.clone() }
+            }
+        };
+
+        // leaves updated after this tree was committed
+        if trie_node.hash::<H>() != *hash {
+            return Ok(());
+        }
+
+        let preimage = match trie_node {
+            TrieNode::Binary { left, right } => vec![left, right],
+            TrieNode::Edge { child, path } => {
+                vec![StarkFelt::from(path.len() as u64), felt_from_bits_api(&path)?, child]
+            }
+        };
+        facts.insert(*hash, preimage);
+
+        Ok(())
+    }
 }
@@ -10,6 +10,7 @@ use regex::Regex;
 use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Number;
 use serde_with::{DeserializeAs, SerializeAs};
+use sha3::{Digest, Keccak256};
 use starknet_api::core::{ChainId, ClassHash, Nonce, PatriciaKey};
 use starknet_api::deprecated_contract_class::{ContractClass as DeprecatedContractClass, Program as DeprecatedProgram};
 use starknet_api::hash::{pedersen_hash, StarkFelt, StarkHash};
@@ -62,14 +63,25 @@ pub fn felt_api2vm(felt: StarkFelt) -> Felt252 {
 pub fn felt_vm2usize(felt_op: Option<&Felt252>) -> Result<usize, SnOsError> {
     match felt_op {
         Some(felt) => {
-            let big_num: u16 = felt.to_bigint().try_into().map_err(|e| SnOsError::Output(format!("{e}")))?;
+            let big_num: usize =
+                felt.to_bigint().try_into().map_err(|e| SnOsError::CatchAll(format!("felt to usize - {e}")))?;
 
-            Ok(big_num.into())
+            Ok(big_num)
         }
         None => Err(SnOsError::CatchAll("no length available".to_string())),
     }
 }
 
+/// Keccak256 of the felts encoded as big endian 32 bytes words, i.e. `abi.encodePacked` of
+/// `uint256`s.
+pub fn keccak_felts<'a>(felts: impl IntoIterator<Item = &'a Felt252>) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    for felt in felts {
+        hasher.update(felt.to_bytes_be());
+    }
+    hasher.finalize().into()
+}
+
 pub fn deprecated_class_vm2api(class: &ContractClassV0Inner) -> DeprecatedContractClass {
     let builtins = class.program.iter_builtins().cloned().collect::<Vec<_>>();
     let data = class.program.iter_data().cloned().collect::<Vec<_>>();
@@ -1,5 +1,6 @@
 mod common;
 
+use std::collections::HashSet;
 use std::fs;
 use std::rc::Rc;
 
@@ -13,7 +14,7 @@ use common::{load_input, load_output};
 use rstest::{fixture, rstest};
 use snos::hints::block_context::*;
 use snos::hints::{
-    initialize_class_hashes, initialize_state_changes, starknet_os_input, INITIALIZE_CLASS_HASHES,
+    execution, initialize_class_hashes, initialize_state_changes, starknet_os_input, INITIALIZE_CLASS_HASHES,
     INITIALIZE_STATE_CHANGES, STARKNET_OS_INPUT,
 };
 use snos::io::input::StarknetOsInput;
@@ -111,3 +112,39 @@ fn format_os_output_test(mut os_input_hint_processor: BuiltinHintProcessor, load
     let os_output = StarknetOsOutput::from_run(&vm).unwrap();
     assert_eq!(load_output.config_hash, os_output.config_hash);
 }
+
+#[rstest]
+fn execution_hints_match_os_program() {
+    let os_program: serde_json::Value = serde_json::from_slice(&fs::read("build/os_latest.json").unwrap()).unwrap();
+    let os_hints: HashSet<&str> = os_program["hints"]
+        .as_object()
+        .unwrap()
+        .values()
+        .flat_map(|hints| hints.as_array().unwrap())
+        .map(|hint| hint["code"].as_str().unwrap())
+        .collect();
+
+    for hint in [
+        execution::TX_NONCE,
+        execution::TX_NONCE_ARG,
+        execution::TX_ADDITIONAL_DATA_NONCE,
+        execution::TX_MAX_FEE,
+        execution::TX_ENTRY_POINT_SELECTOR,
+        execution::TX_CALLDATA_LEN,
+        execution::TX_CALLDATA,
+        execution::TX_SIGNATURE,
+        execution::IS_L1_HANDLER_CONTRACT_ADDRESS,
+        execution::DECLARE_TX_FIELDS,
+        execution::START_DEPLOY_TX,
+        execution::START_VALIDATE_DECLARE_TX,
+        execution::START_TX,
+        execution::START_INVOKE_TX,
+        execution::SKIP_TX,
+        execution::SKIP_CALL,
+        execution::TX_ACTUAL_FEE,
+        execution::TX_IS_REVERTED,
+        execution::IS_ENOUGH_GAS,
+    ] {
+        assert!(os_hints.contains(hint), "hint not found in the OS program:\n{hint}");
+    }
+}
@@ -1,23 +1,32 @@
 mod common;
 
+use std::collections::HashMap;
 use std::fs;
 
 use blockifier::block_context::BlockContext;
+use blockifier::execution::call_info::CallInfo;
 use blockifier::test_utils::dict_state_reader::DictStateReader;
 use blockifier::transaction::objects::TransactionExecutionInfo;
 use cairo_vm::cairo_run::{cairo_run, CairoRunConfig};
+use cairo_vm::hint_processor::hint_processor_definition::HintReference;
+use cairo_vm::serde::deserialize_program::ApTracking;
+use cairo_vm::types::exec_scope::ExecutionScopes;
 use cairo_vm::types::program::Program;
+use cairo_vm::types::relocatable::{MaybeRelocatable, Relocatable};
+use cairo_vm::vm::errors::hint_errors::HintError;
 use cairo_vm::vm::errors::vm_exception::VmException;
 use cairo_vm::vm::runners::cairo_runner::CairoRunner;
 use cairo_vm::vm::vm_core::VirtualMachine;
+use cairo_vm::Felt252;
 use common::load_input;
 use common::prepared_os_test::{block_context, prepare_os_test};
 use common::utils::check_output_vs_python;
 use rstest::rstest;
 use snos::execution::deprecated_syscall_handler::DeprecatedOsSyscallHandlerWrapper;
 use snos::execution::helper::ExecutionHelperWrapper;
-use snos::hints::SnosHintProcessor;
+use snos::hints::{execution, SnosHintProcessor};
 use snos::io::input::StarknetOsInput;
+use snos::io::{InternalDeclare, InternalInvokeFunction, InternalTransaction};
 use snos::state::SharedState;
 use starknet_api::block::{BlockNumber, BlockTimestamp};
 
@@ -158,3 +167,239 @@ fn block_context_test(
     println!("Program output:");
     println!("{rs_output}");
 }
+
+type HintImpl = fn(
+    &mut VirtualMachine,
+    &mut ExecutionScopes,
+    &HashMap<String, HintReference>,
+    &ApTracking,
+    &HashMap<String, Felt252>,
+) -> Result<(), HintError>;
+
+/// VM whose frame holds `ids` in consecutive cells from `fp`, with `ap` right after them.
+fn hint_frame(ids: &[&str]) -> (VirtualMachine, HashMap<String, HintReference>) {
+    let mut vm = VirtualMachine::new(false);
+    // program and execution segments
+    vm.add_memory_segment();
+    vm.add_memory_segment();
+    vm.set_fp(0);
+    vm.set_ap(ids.len());
+
+    let ids_data =
+        ids.iter().enumerate().map(|(offset, id)| (id.to_string(), HintReference::new_simple(offset as i32))).collect();
+    (vm, ids_data)
+}
+
+fn tx_scope(tx: &InternalTransaction) -> ExecutionScopes {
+    let mut exec_scopes = ExecutionScopes::new();
+    exec_scopes.insert_value("tx", tx.clone());
+    exec_scopes
+}
+
+fn find_tx(load_input: &StarknetOsInput, tx_type: &str) -> InternalTransaction {
+    load_input.transactions.iter().find(|tx| tx.tx_type() == tx_type).unwrap().clone()
+}
+
+/// Runs a hint writing to `memory[ap]` for `tx` and returns the written value.
+fn ap_hint_value(hint: HintImpl, tx: &InternalTransaction) -> Result<MaybeRelocatable, HintError> {
+    let (mut vm, ids_data) = hint_frame(&[]);
+    hint(&mut vm, &mut tx_scope(tx), &ids_data, &ApTracking::new(), &HashMap::new())?;
+    Ok(vm.get_maybe(&vm.get_ap()).unwrap())
+}
+
+/// Felts of the segment `ptr` points to, `len` of them.
+fn read_felts(vm: &VirtualMachine, ptr: &MaybeRelocatable, len: usize) -> Vec<Felt252> {
+    let MaybeRelocatable::RelocatableValue(ptr) = ptr else { panic!("expected a pointer, got {ptr}") };
+    vm.get_integer_range(*ptr, len).unwrap().into_iter().map(|felt| felt.into_owned()).collect()
+}
+
+#[rstest]
+fn tx_field_hints(load_input: &StarknetOsInput) {
+    let invoke = find_tx(load_input, "INVOKE_FUNCTION");
+    let InternalTransaction::InvokeFunction(InternalInvokeFunction::V1(invoke_v1)) = &invoke else {
+        panic!("expected an invoke v1")
+    };
+    let felt = |felt: Felt252| MaybeRelocatable::Int(felt);
+
+    assert_eq!(felt(invoke_v1.nonce), ap_hint_value(execution::tx_nonce, &invoke).unwrap());
+    assert_eq!(felt(invoke_v1.nonce), ap_hint_value(execution::tx_nonce_arg, &invoke).unwrap());
+    assert_eq!(felt(invoke_v1.max_fee), ap_hint_value(execution::tx_max_fee, &invoke).unwrap());
+    assert_eq!(
+        felt(invoke_v1.entry_point_selector),
+        ap_hint_value(execution::tx_entry_point_selector, &invoke).unwrap()
+    );
+    assert_eq!(
+        felt(Felt252::from(invoke_v1.calldata.len())),
+        ap_hint_value(execution::tx_calldata_len, &invoke).unwrap()
+    );
+
+    let (mut vm, ids_data) = hint_frame(&[]);
+    execution::tx_calldata(&mut vm, &mut tx_scope(&invoke), &ids_data, &ApTracking::new(), &HashMap::new()).unwrap();
+    let calldata = vm.get_maybe(&vm.get_ap()).unwrap();
+    assert_eq!(invoke_v1.calldata, read_felts(&vm, &calldata, invoke_v1.calldata.len()));
+
+    let (mut vm, ids_data) = hint_frame(&[]);
+    execution::tx_additional_data_nonce(
+        &mut vm,
+        &mut tx_scope(&invoke),
+        &ids_data,
+        &ApTracking::new(),
+        &HashMap::new(),
+    )
+    .unwrap();
+    let additional_data = vm.get_maybe(&vm.get_ap()).unwrap();
+    assert_eq!(vec![invoke_v1.nonce], read_felts(&vm, &additional_data, 1));
+
+    // deploy transactions carry neither a nonce nor a fee
+    let deploy = find_tx(load_input, "DEPLOY");
+    assert_eq!(felt(Felt252::ZERO), ap_hint_value(execution::tx_nonce, &deploy).unwrap());
+    assert!(ap_hint_value(execution::tx_nonce_arg, &deploy).is_err());
+    assert!(ap_hint_value(execution::tx_max_fee, &deploy).is_err());
+    assert!(ap_hint_value(execution::tx_calldata_len, &deploy).is_err());
+}
+
+#[rstest]
+fn tx_signature_hint(load_input: &StarknetOsInput) {
+    let mut invoke = find_tx(load_input, "INVOKE_FUNCTION");
+    let signature = vec![Felt252::from(100), Felt252::from(200)];
+    if let InternalTransaction::InvokeFunction(InternalInvokeFunction::V1(invoke_v1)) = &mut invoke {
+        invoke_v1.signature = signature.clone();
+    }
+
+    let (mut vm, ids_data) = hint_frame(&["signature_start", "signature_len"]);
+    execution::tx_signature(&mut vm, &mut tx_scope(&invoke), &ids_data, &ApTracking::new(), &HashMap::new()).unwrap();
+
+    let signature_start = vm.get_maybe(&Relocatable::from((1, 0))).unwrap();
+    assert_eq!(signature, read_felts(&vm, &signature_start, 2));
+    assert_eq!(Felt252::TWO, *vm.get_integer(Relocatable::from((1, 1))).unwrap());
+}
+
+#[rstest]
+fn is_l1_handler_contract_address_hint(load_input: &StarknetOsInput) {
+    let l1_handler = find_tx(load_input, "L1_HANDLER");
+    let InternalTransaction::L1Handler(l1_handler_tx) = &l1_handler else { panic!("expected an L1 handler") };
+    let invoke = find_tx(load_input, "INVOKE_FUNCTION");
+
+    for (tx, expected_address) in
+        [(&l1_handler, l1_handler_tx.contract_address), (&invoke, invoke.sender_address().unwrap())]
+    {
+        let (mut vm, ids_data) = hint_frame(&["contract_address"]);
+        execution::is_l1_handler_contract_address(
+            &mut vm,
+            &mut tx_scope(tx),
+            &ids_data,
+            &ApTracking::new(),
+            &HashMap::new(),
+        )
+        .unwrap();
+        assert_eq!(expected_address, *vm.get_integer(Relocatable::from((1, 0))).unwrap());
+    }
+}
+
+#[rstest]
+fn declare_tx_fields_hint(load_input: &StarknetOsInput) {
+    let declare = find_tx(load_input, "DECLARE");
+    let InternalTransaction::Declare(InternalDeclare::V1(declare_v1)) = &declare else {
+        panic!("expected a declare v1")
+    };
+
+    let ids = ["tx_version", "max_fee", "sender_address", "calldata", "additional_data"];
+    let (mut vm, ids_data) = hint_frame(&ids);
+    execution::declare_tx_fields(&mut vm, &mut tx_scope(&declare), &ids_data, &ApTracking::new(), &HashMap::new())
+        .unwrap();
+
+    assert_eq!(Felt252::ONE, *vm.get_integer(Relocatable::from((1, 0))).unwrap());
+    assert_eq!(declare_v1.max_fee, *vm.get_integer(Relocatable::from((1, 1))).unwrap());
+    assert_eq!(declare_v1.sender_address, *vm.get_integer(Relocatable::from((1, 2))).unwrap());
+    let calldata = vm.get_maybe(&Relocatable::from((1, 3))).unwrap();
+    assert_eq!(vec![declare_v1.class_hash], read_felts(&vm, &calldata, 1));
+    // deprecated declares do not commit to a compiled class hash
+    let additional_data = vm.get_maybe(&Relocatable::from((1, 4))).unwrap();
+    assert_eq!(vec![declare_v1.nonce], read_felts(&vm, &additional_data, 1));
+    assert!(vm.get_maybe(&(additional_data.get_relocatable().unwrap() + 1usize).unwrap()).is_none());
+
+    let invoke = find_tx(load_input, "INVOKE_FUNCTION");
+    let (mut vm, ids_data) = hint_frame(&ids);
+    assert!(execution::declare_tx_fields(
+        &mut vm,
+        &mut tx_scope(&invoke),
+        &ids_data,
+        &ApTracking::new(),
+        &HashMap::new()
+    )
+    .is_err());
+}
+
+#[rstest]
+fn start_tx_hints(block_context: BlockContext) {
+    // ExecutionContext: entry_point_type, class_hash, calldata_size, calldata, execution_info,
+    // deprecated_tx_info
+    let start_hints: [(&str, HintImpl); 4] = [
+        ("constructor_execution_context", execution::start_deploy_tx),
+        ("validate_declare_execution_context", execution::start_validate_declare_tx),
+        ("tx_execution_context", execution::start_invoke_tx),
+        ("deprecated_tx_info", execution::start_tx),
+    ];
+
+    for (id, hint) in start_hints {
+        let (mut vm, ids_data) = hint_frame(&[id]);
+        let tx_info_ptr = vm.add_memory_segment();
+        if id == "deprecated_tx_info" {
+            vm.insert_value(Relocatable::from((1, 0)), tx_info_ptr).unwrap();
+        } else {
+            let execution_context = vm.add_memory_segment();
+            vm.insert_value((execution_context + 5usize).unwrap(), tx_info_ptr).unwrap();
+            vm.insert_value(Relocatable::from((1, 0)), execution_context).unwrap();
+        }
+
+        let execution_helper = ExecutionHelperWrapper::new(vec![TransactionExecutionInfo::default()], &block_context);
+        let mut exec_scopes = ExecutionScopes::new();
+        exec_scopes.insert_value("execution_helper", execution_helper.clone());
+        hint(&mut vm, &mut exec_scopes, &ids_data, &ApTracking::new(), &HashMap::new()).unwrap();
+
+        let helper = execution_helper.execution_helper.borrow();
+        assert_eq!(Some(tx_info_ptr), helper.tx_info_ptr, "{id}");
+        assert!(helper.tx_execution_info.is_some(), "{id}");
+    }
+}
+
+#[rstest]
+fn skip_tx_hint(block_context: BlockContext) {
+    let execution_helper = ExecutionHelperWrapper::new(vec![TransactionExecutionInfo::default(); 2], &block_context);
+    let mut exec_scopes = ExecutionScopes::new();
+    exec_scopes.insert_value("execution_helper", execution_helper.clone());
+
+    let (mut vm, ids_data) = hint_frame(&[]);
+    execution::skip_tx(&mut vm, &mut exec_scopes, &ids_data, &ApTracking::new(), &HashMap::new()).unwrap();
+
+    // the skipped transaction is consumed without leaving a transaction in progress
+    let helper = execution_helper.execution_helper.borrow();
+    assert!(helper.tx_execution_info.is_none());
+    assert!(helper.tx_info_ptr.is_none());
+    assert_eq!(1, helper.tx_execution_info_iter.len());
+}
+
+#[rstest]
+fn skip_call_hint(block_context: BlockContext) {
+    let tx_execution_info = TransactionExecutionInfo {
+        validate_call_info: Some(CallInfo::default()),
+        execute_call_info: Some(CallInfo::default()),
+        ..Default::default()
+    };
+    let mut execution_helper = ExecutionHelperWrapper::new(vec![tx_execution_info], &block_context);
+    execution_helper.start_tx(None);
+    let mut exec_scopes = ExecutionScopes::new();
+    exec_scopes.insert_value("execution_helper", execution_helper.clone());
+
+    let (mut vm, ids_data) = hint_frame(&[]);
+    execution::skip_call(&mut vm, &mut exec_scopes, &ids_data, &ApTracking::new(), &HashMap::new()).unwrap();
+
+    // the skipped call is consumed without leaving a call in progress
+    {
+        let helper = execution_helper.execution_helper.borrow();
+        assert!(helper.call_info.is_none());
+        assert_eq!(1, helper.call_iter.len());
+    }
+    execution_helper.skip_call();
+    execution_helper.end_tx();
+}
@@ -1,14 +1,37 @@
 mod common;
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek};
 use std::path::Path;
 
+use base64::engine::general_purpose;
+use base64::Engine as _;
+use cairo_vm::serde::deserialize_program::BuiltinName;
+use cairo_vm::types::program::Program;
+use cairo_vm::types::relocatable::MaybeRelocatable;
 use cairo_vm::vm::runners::builtin_runner::OUTPUT_BUILTIN_NAME;
-use cairo_vm::vm::runners::cairo_pie::{BuiltinAdditionalData, CairoPie, OutputBuiltinAdditionalData, SegmentInfo};
+use cairo_vm::vm::runners::cairo_pie::{
+    BuiltinAdditionalData, CairoPie, OutputBuiltinAdditionalData, PublicMemoryPage, SegmentInfo,
+};
 use cairo_vm::vm::runners::cairo_runner::ExecutionResources;
-use common::{os_pie_string, setup_pie};
+use cairo_vm::Felt252;
+use common::{load_output, os_pie_string, setup_pie};
 use rstest::rstest;
 use serde_json::json;
-use snos::sharp::pie::{decode_base64_to_unzipped, PIE_FILES};
+use snos::config::DEFAULT_LAYOUT;
+use snos::error::{OutputError, SnOsError};
+use snos::io::output::StarknetOsOutput;
+use snos::sharp::aggregate::{split_os_outputs, split_output, BootloaderInput, TaskOutput};
+use snos::sharp::fact::{
+    compute_program_hash_chain, pie_fact, pie_program_hash, program_hash, FactTopology, BOOTLOADER_VERSION,
+    GPS_FACT_TOPOLOGY,
+};
+use snos::sharp::pie::{
+    decode_base64_to_unzipped, decode_pie, encode_pie, encode_pie_mem, read_pie_zip, validate, validate_with_limit,
+    write_pie_zip, PIE_FILES,
+};
+use snos::sharp::InvalidReason;
+use zip::ZipArchive;
 
 #[rstest]
 fn pie_metadata_ok(setup_pie: CairoPie) {
@@ -90,3 +113,155 @@ fn convert_b64_to_raw(os_pie_string: String) {
         );
     }
 }
+
+#[rstest]
+fn os_output_from_pie(setup_pie: CairoPie, load_output: StarknetOsOutput) {
+    let raw_output = load_output.encode().unwrap();
+
+    let mut os_pie = setup_pie;
+    let output_segment = os_pie.metadata.builtin_segments.get_mut("output").unwrap();
+    output_segment.size = raw_output.len();
+    let output_index = output_segment.index as usize;
+    os_pie.memory.0.retain(|((segment_index, _), _)| *segment_index != output_index);
+    os_pie.memory.0.extend(
+        raw_output.iter().enumerate().map(|(offset, felt)| ((output_index, offset), MaybeRelocatable::Int(*felt))),
+    );
+    assert_eq!(load_output, StarknetOsOutput::from_pie(&os_pie).unwrap());
+
+    os_pie.metadata.builtin_segments.clear();
+    assert!(matches!(StarknetOsOutput::from_pie(&os_pie), Err(SnOsError::Output(OutputError::MissingOutputSegment))));
+}
+
+#[rstest]
+fn pie_program_hash_matches_program(setup_pie: CairoPie) {
+    let program = Program::from_bytes(&std::fs::read("build/programs/fact.json").unwrap(), Some("main")).unwrap();
+
+    let hash = program_hash(&program).unwrap();
+    assert_eq!(hash, pie_program_hash(&setup_pie).unwrap());
+    assert_eq!(hash, compute_program_hash_chain(&setup_pie.metadata.program, BOOTLOADER_VERSION, false).unwrap());
+    assert_ne!(hash, compute_program_hash_chain(&setup_pie.metadata.program, BOOTLOADER_VERSION + 1, false).unwrap());
+    assert_ne!(hash, compute_program_hash_chain(&setup_pie.metadata.program, BOOTLOADER_VERSION, true).unwrap());
+}
+
+#[rstest]
+fn validate_pie(setup_pie: CairoPie) {
+    assert_eq!(Ok(()), validate(&setup_pie, "small"));
+    assert_eq!(Err(InvalidReason::NO_COMPATIBLE_LAYOUT), validate(&setup_pie, "plain"));
+    assert_eq!(Err(InvalidReason::NO_COMPATIBLE_LAYOUT), validate(&setup_pie, "not_a_layout"));
+
+    // the limit applies to the base64 encoding sent to SHARP
+    let encoded_size = encode_pie_mem(&setup_pie).unwrap().len() as u64;
+    assert_eq!(Ok(()), validate_with_limit(&setup_pie, "small", encoded_size));
+    assert_eq!(
+        Err(InvalidReason::INVALID_CAIRO_PIE_FILE_FORMAT),
+        validate_with_limit(&setup_pie, "small", encoded_size - 1)
+    );
+
+    let mut bad_order = setup_pie.clone();
+    bad_order.metadata.program.builtins = vec![BuiltinName::pedersen, BuiltinName::output];
+    assert_eq!(Err(InvalidReason::INVALID_BUILTIN_ORDER_DECLERATION), validate(&bad_order, "small"));
+
+    let mut bad_usage = setup_pie.clone();
+    bad_usage.metadata.program.builtins = vec![BuiltinName::output, BuiltinName::pedersen];
+    assert_eq!(Err(InvalidReason::INVALID_BUILTIN_USAGE), validate(&bad_usage, "small"));
+
+    // the output pages must cover the 3 output cells
+    let mut bad_pages = setup_pie;
+    bad_pages.additional_data.insert(
+        OUTPUT_BUILTIN_NAME.to_string(),
+        BuiltinAdditionalData::Output(OutputBuiltinAdditionalData {
+            pages: HashMap::from([(1, PublicMemoryPage { start: 1, size: 1 })]),
+            attributes: HashMap::from([(GPS_FACT_TOPOLOGY.to_string(), vec![2, 1])]),
+        }),
+    );
+    assert_eq!(Err(InvalidReason::FAILED_TO_GENERATE_FACT), validate(&bad_pages, "small"));
+}
+
+#[rstest]
+fn decode_encoded_pie(setup_pie: CairoPie, os_pie_string: String, load_output: StarknetOsOutput) {
+    let decoded = decode_pie(&encode_pie_mem(&setup_pie).unwrap()).unwrap();
+    assert_eq!(setup_pie.metadata, decoded.metadata);
+    assert_eq!(setup_pie.memory, decoded.memory);
+    assert_eq!(setup_pie.additional_data, decoded.additional_data);
+    assert_eq!(setup_pie.execution_resources, decoded.execution_resources);
+
+    let dst = Path::new("build/decode_encoded_pie.zip");
+    encode_pie(&setup_pie, dst).unwrap();
+    assert_eq!(setup_pie.memory, read_pie_zip(dst).unwrap().memory);
+
+    let os_pie = decode_pie(&os_pie_string).unwrap();
+    assert_eq!(load_output, StarknetOsOutput::from_pie(&os_pie).unwrap());
+    assert_eq!(Ok(()), validate(&os_pie, DEFAULT_LAYOUT));
+
+    assert!(matches!(decode_pie("bm90IGEgemlw"), Err(SnOsError::PieParsing(_))));
+    assert!(matches!(decode_base64_to_unzipped("bm90IGEgemlw", "build/pie/"), Err(SnOsError::PieZipping(_))));
+}
+
+#[rstest]
+fn write_pie_zip_memory_layout(os_pie_string: String) {
+    let os_pie = decode_pie(&os_pie_string).unwrap();
+    let dst = Path::new("build/write_pie_zip.zip");
+    write_pie_zip(&os_pie, dst).unwrap();
+
+    fn read_memory_bin<R: Read + Seek>(zip: &mut ZipArchive<R>) -> Vec<u8> {
+        let mut memory = vec![];
+        zip.by_name("memory.bin").unwrap().read_to_end(&mut memory).unwrap();
+        memory
+    }
+    let mut written = ZipArchive::new(File::open(dst).unwrap()).unwrap();
+    let mut original = ZipArchive::new(Cursor::new(general_purpose::STANDARD.decode(&os_pie_string).unwrap())).unwrap();
+    assert_eq!(read_memory_bin(&mut original), read_memory_bin(&mut written));
+
+    let read_back = read_pie_zip(dst).unwrap();
+    assert_eq!(os_pie.metadata, read_back.metadata);
+    assert_eq!(os_pie.additional_data, read_back.additional_data);
+}
+
+#[rstest]
+fn aggregate_os_pies(os_pie_string: String, load_output: StarknetOsOutput) {
+    let os_pie = decode_pie(&os_pie_string).unwrap();
+    let input = BootloaderInput::new(vec![os_pie.clone()]).merge(BootloaderInput::new(vec![os_pie.clone()]));
+
+    let program_hash = pie_program_hash(&os_pie).unwrap();
+    let os_output = load_output.encode().unwrap();
+    let output = input.output().unwrap();
+    assert_eq!(1 + 2 * (2 + os_output.len()), output.len());
+    assert_eq!(Felt252::from(2), output[0]);
+    assert_eq!(Felt252::from(2 + os_output.len()), output[1]);
+    assert_eq!(program_hash, output[2]);
+
+    let tasks = split_output(&output).unwrap();
+    assert_eq!(input.task_outputs().unwrap(), tasks);
+    assert_eq!(TaskOutput { program_hash, output: os_output.clone() }, tasks[0]);
+    assert_eq!(vec![load_output.clone(), load_output], split_os_outputs(&output).unwrap());
+
+    // each task fact follows the output pages of its PIE
+    let os_fact = pie_fact(&os_pie).unwrap();
+    assert_eq!(vec![os_fact, os_fact], input.task_facts().unwrap());
+    assert_ne!(os_fact, tasks[1].fact(&FactTopology::single_page(os_output.len())).unwrap());
+
+    assert!(split_output(&output[..output.len() - 1]).is_err());
+    assert!(matches!(
+        split_output(&[Felt252::ONE, Felt252::ONE]),
+        Err(SnOsError::Output(OutputError::InvalidLength(1, _)))
+    ));
+
+    let dir = std::env::temp_dir().join(format!("snos_aggregate_{}", std::process::id()));
+    let input_path = input.write(&dir).unwrap();
+    let input_json: serde_json::Value = serde_json::from_slice(&std::fs::read(input_path).unwrap()).unwrap();
+    assert_eq!(2, input_json["tasks"].as_array().unwrap().len());
+    assert_eq!(json!(false), input_json["tasks"][0]["use_poseidon"]);
+    let task_path = input_json["tasks"][1]["path"].as_str().unwrap();
+    assert_eq!(os_pie.memory, read_pie_zip(Path::new(task_path)).unwrap().memory);
+
+    // the program hashes written by the bootloader follow the task hash function
+    let poseidon_input = input.with_poseidon(true);
+    let poseidon_hash = compute_program_hash_chain(&os_pie.metadata.program, BOOTLOADER_VERSION, true).unwrap();
+    assert_eq!(poseidon_hash, poseidon_input.output().unwrap()[2]);
+    assert_ne!(vec![os_fact, os_fact], poseidon_input.task_facts().unwrap());
+    let input_json: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(poseidon_input.write(&dir).unwrap()).unwrap()).unwrap();
+    assert_eq!(json!(true), input_json["tasks"][0]["use_poseidon"]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -1,8 +1,19 @@
 mod common;
 
-use common::os_pie_string;
+use std::time::Duration;
+
+use cairo_vm::Felt252;
+use common::{load_output, os_pie_string};
 use rstest::rstest;
-use snos::sharp::{CairoJobStatus, InvalidReason, SharpClient, SharpPie};
+use snos::error::{SharpError, SnOsError};
+use snos::io::output::StarknetOsOutput;
+use snos::sharp::fact::{compute_fact, compute_hash_chain, pie_fact, pie_program_hash, FactTopology};
+use snos::sharp::mock::{MockFailure, MockSharp};
+use snos::sharp::pie::decode_pie;
+use snos::sharp::{
+    AsyncSharpClient, CairoJobStatus, CairoStatusResponse, InvalidReason, JobOutcome, PollPolicy, SharpClient,
+    SharpClientBuilder, SharpPie,
+};
 use uuid::uuid;
 
 #[rstest]
@@ -33,6 +44,51 @@ fn sharp_get_status() {
     assert_eq!(CairoJobStatus::PROCESSED, status_resp.status);
 }
 
+#[tokio::test]
+#[ignore]
+async fn async_sharp_get_status() {
+    let good_cairo_job_id = uuid!("3a24bbca-ad75-49d5-8ced-12796c6c0738");
+
+    let sharp_client = AsyncSharpClient::default();
+    let status_resp = sharp_client.get_status(&good_cairo_job_id).await.unwrap();
+
+    assert_eq!(1, status_resp.version);
+    assert_eq!(CairoJobStatus::PROCESSED, status_resp.status);
+}
+
+#[rstest]
+#[ignore]
+fn sharp_wait_for_job() {
+    let good_cairo_job_id = uuid!("3a24bbca-ad75-49d5-8ced-12796c6c0738");
+
+    let sharp_client = SharpClient::default();
+    let mut transitions = vec![];
+    let outcome = sharp_client
+        .wait_for_job_with(&good_cairo_job_id, &PollPolicy::default(), |from, to| {
+            transitions.push((from.clone(), to.clone()))
+        })
+        .unwrap();
+
+    assert_eq!(JobOutcome::Processed, outcome);
+    assert_eq!(vec![(CairoJobStatus::UNKNOWN, CairoJobStatus::PROCESSED)], transitions);
+}
+
+#[rstest]
+fn sharp_unreachable() {
+    let job_id = uuid!("3a24bbca-ad75-49d5-8ced-12796c6c0738");
+
+    let sharp_client = SharpClient::with_sharp_addr("http://127.0.0.1:1");
+    assert!(matches!(sharp_client.get_status(&job_id), Err(SnOsError::Sharp(SharpError::Network(_)))));
+    assert!(matches!(
+        sharp_client.submit_pie(SharpPie::EncodedPie(String::new())),
+        Err(SnOsError::Sharp(SharpError::Network(_)))
+    ));
+
+    let policy =
+        PollPolicy { initial_interval: Duration::from_millis(1), max_consecutive_errors: 2, ..PollPolicy::default() };
+    assert!(sharp_client.wait_for_job(&job_id, &policy).is_err());
+}
+
 #[rstest]
 #[ignore]
 fn sharp_get_status_err() {
@@ -45,3 +101,223 @@ fn sharp_get_status_err() {
     assert_eq!(CairoJobStatus::INVALID, status_resp.status);
     assert_eq!(InvalidReason::SECURITY_CHECK_FAILURE, status_resp.invalid_reason.unwrap());
 }
+
+#[rstest]
+fn os_output_fact_hash(os_pie_string: String, load_output: StarknetOsOutput) {
+    let a = Felt252::from_hex("0x03d937c035c878245caf64531a5756109c53068da139362728feb561405371cb").unwrap();
+    let b = Felt252::from_hex("0x0208a0a10250e382e1e4bbe2880906c2791bf6275695e02fbbc6aeff9cd8b31a").unwrap();
+    assert_eq!(
+        Felt252::from_hex("0x030e480bed5fe53fa909cc0f8c4d99b8f9f2c016be4c41e13a4848797979c662").unwrap(),
+        compute_hash_chain(&[a, b]).unwrap()
+    );
+    assert!(compute_hash_chain(&[]).is_err());
+
+    // the OS writes its data availability part in a separate page, under its own node
+    let os_pie = decode_pie(&os_pie_string).unwrap();
+    let topology = FactTopology::from_pie(&os_pie).unwrap();
+    assert_eq!(FactTopology { tree_structure: vec![2, 1, 0, 2], page_sizes: vec![18, 46] }, topology);
+
+    let os_output = load_output.encode().unwrap();
+    let fact = compute_fact(a, &os_output, &topology).unwrap();
+    assert_eq!("84c083dbcb98390e9a620d481dc07e6d0a077bf852bc6b39cc612d7d23d678ba", hex::encode(fact));
+    assert_eq!(
+        compute_fact(pie_program_hash(&os_pie).unwrap(), &os_output, &topology).unwrap(),
+        pie_fact(&os_pie).unwrap()
+    );
+
+    // a single page tree is the keccak of the whole output
+    let fact = compute_fact(a, &os_output, &FactTopology::single_page(os_output.len())).unwrap();
+    assert_eq!("a7d8871c42f55e134f9cc27a65c28d619ea71fe67e6d2da3863c2c6d0e8f10df", hex::encode(fact));
+
+    for tree_structure in [vec![2, 1], vec![1, 0, 1, 0], vec![2, 3], vec![1, 0]] {
+        let topology = FactTopology { tree_structure, page_sizes: vec![18, 46] };
+        assert!(compute_fact(a, &os_output, &topology).is_err());
+    }
+    let topology = FactTopology { tree_structure: vec![2, 2], page_sizes: vec![18, 45] };
+    assert!(compute_fact(a, &os_output, &topology).is_err());
+}
+
+fn fast_policy() -> PollPolicy {
+    PollPolicy {
+        initial_interval: Duration::from_millis(1),
+        max_interval: Duration::from_millis(10),
+        timeout: Duration::from_secs(10),
+        ..PollPolicy::default()
+    }
+}
+
+#[rstest]
+fn mock_sharp_job_lifecycle() {
+    let mock = MockSharp::start().unwrap();
+    mock.set_progression([
+        CairoJobStatus::IN_PROGRESS.into(),
+        CairoJobStatus::IN_PROGRESS.into(),
+        CairoJobStatus::PROCESSED.into(),
+    ]);
+
+    let sharp_client = SharpClient::with_sharp_addr(&mock.url());
+    let job_key = sharp_client.submit_pie(SharpPie::EncodedPie("pie".to_string())).unwrap().cairo_job_key.unwrap();
+    assert_eq!(vec![job_key], mock.jobs());
+
+    let mut transitions = vec![];
+    let outcome = sharp_client
+        .wait_for_job_with(&job_key, &fast_policy(), |from, to| transitions.push((from.clone(), to.clone())))
+        .unwrap();
+
+    assert_eq!(JobOutcome::Processed, outcome);
+    assert_eq!(
+        vec![
+            (CairoJobStatus::UNKNOWN, CairoJobStatus::IN_PROGRESS),
+            (CairoJobStatus::IN_PROGRESS, CairoJobStatus::PROCESSED)
+        ],
+        transitions
+    );
+    assert_eq!(4, mock.n_requests());
+
+    let unknown_job = uuid!("43454c8e-8f43-444f-aab2-edab05bef512");
+    assert_eq!(CairoJobStatus::NOT_CREATED, sharp_client.get_status(&unknown_job).unwrap().status);
+}
+
+#[rstest]
+fn mock_sharp_invalid_job() {
+    let mock = MockSharp::start().unwrap();
+    mock.set_progression([
+        CairoJobStatus::IN_PROGRESS.into(),
+        CairoStatusResponse {
+            invalid_reason: Some(InvalidReason::SECURITY_CHECK_FAILURE),
+            ..CairoJobStatus::INVALID.into()
+        },
+    ]);
+
+    let sharp_client = SharpClient::with_sharp_addr(&mock.url());
+    let job_key = sharp_client.submit_pie(SharpPie::EncodedPie("pie".to_string())).unwrap().cairo_job_key.unwrap();
+
+    let outcome = sharp_client.wait_for_job(&job_key, &fast_policy()).unwrap();
+    assert_eq!(JobOutcome::Invalid(Some(InvalidReason::SECURITY_CHECK_FAILURE)), outcome);
+}
+
+#[rstest]
+fn mock_sharp_failures() {
+    let mock = MockSharp::start().unwrap();
+    let sharp_client = SharpClient::with_sharp_addr(&mock.url());
+
+    mock.fail_next(MockFailure::Http(500, "internal error".to_string()));
+    assert!(sharp_client.submit_pie(SharpPie::EncodedPie("pie".to_string())).is_err());
+    let job_key = sharp_client.submit_pie(SharpPie::EncodedPie("pie".to_string())).unwrap().cairo_job_key.unwrap();
+
+    // transient errors are retried
+    mock.fail_next(MockFailure::Http(503, "unavailable".to_string()));
+    mock.fail_next(MockFailure::Http(503, "unavailable".to_string()));
+    assert_eq!(JobOutcome::Processed, sharp_client.wait_for_job(&job_key, &fast_policy()).unwrap());
+
+    for _ in 0..2 {
+        mock.fail_next(MockFailure::Http(503, "unavailable".to_string()));
+    }
+    let policy = PollPolicy { max_consecutive_errors: 1, ..fast_policy() };
+    assert!(sharp_client.wait_for_job(&job_key, &policy).is_err());
+}
+
+#[tokio::test]
+async fn mock_sharp_timeout() {
+    let mock = MockSharp::start().unwrap();
+    mock.set_progression([CairoJobStatus::IN_PROGRESS.into()]);
+
+    let sharp_client = AsyncSharpClient::with_sharp_addr(&mock.url());
+    let job_key =
+        sharp_client.submit_pie(SharpPie::EncodedPie("pie".to_string())).await.unwrap().cairo_job_key.unwrap();
+
+    let policy = PollPolicy { timeout: Duration::from_millis(20), ..fast_policy() };
+    let outcome = sharp_client.wait_for_job(&job_key, &policy).await.unwrap();
+    assert_eq!(JobOutcome::TimedOut(CairoJobStatus::IN_PROGRESS), outcome);
+}
+
+#[rstest]
+fn mock_sharp_poll_multiplier() {
+    let mock = MockSharp::start().unwrap();
+    mock.set_progression([CairoJobStatus::IN_PROGRESS.into(), CairoJobStatus::PROCESSED.into()]);
+
+    let sharp_client = SharpClient::with_sharp_addr(&mock.url());
+    let job_key = sharp_client.submit_pie(SharpPie::EncodedPie("pie".to_string())).unwrap().cairo_job_key.unwrap();
+
+    for multiplier in [-1.0, 0.5, f64::NAN, f64::INFINITY] {
+        let policy = PollPolicy { multiplier, ..fast_policy() };
+        assert!(matches!(policy.validate(), Err(SharpError::Config(_))));
+        assert!(matches!(sharp_client.wait_for_job(&job_key, &policy), Err(SnOsError::Sharp(SharpError::Config(_)))));
+    }
+    assert_eq!(1, mock.n_requests());
+
+    // an overflowing delay is capped at the max interval
+    let policy = PollPolicy { multiplier: f64::MAX, ..fast_policy() };
+    assert_eq!(JobOutcome::Processed, sharp_client.wait_for_job(&job_key, &policy).unwrap());
+}
+
+#[rstest]
+fn mock_sharp_error_details() {
+    let mock = MockSharp::start().unwrap();
+    let sharp_client = SharpClient::with_sharp_addr(&mock.url());
+    let submit = || sharp_client.submit_pie(SharpPie::EncodedPie("pie".to_string()));
+
+    let body = r#"{"errorType": "InternalError", "errorMessage": "boom", "stackTrace": ["at add_job"]}"#;
+    mock.fail_next(MockFailure::Http(500, body.to_string()));
+    match submit() {
+        Err(SnOsError::Sharp(SharpError::Http { status, error_type, error_message, stack_trace, .. })) => {
+            assert_eq!(500, status);
+            assert_eq!(Some("InternalError".to_string()), error_type);
+            assert_eq!(Some("boom".to_string()), error_message);
+            assert_eq!(Some(vec!["at add_job".to_string()]), stack_trace);
+        }
+        other => panic!("unexpected response {other:?}"),
+    }
+
+    mock.fail_next(MockFailure::Rejected {
+        error_type: "InvalidPie".to_string(),
+        error_message: "bad memory".to_string(),
+    });
+    match submit() {
+        Err(SnOsError::Sharp(e @ SharpError::Rejected { .. })) => {
+            assert!(!e.is_transient());
+            assert!(e.to_string().contains("bad memory"));
+        }
+        other => panic!("unexpected response {other:?}"),
+    }
+
+    mock.fail_next(MockFailure::Http(200, r#"{"version": 1}"#.to_string()));
+    assert!(matches!(submit(), Err(SnOsError::Sharp(SharpError::MissingJobKey))));
+
+    let job_key = submit().unwrap().cairo_job_key.unwrap();
+    mock.fail_next(MockFailure::Http(200, "<html></html>".to_string()));
+    assert!(matches!(sharp_client.get_status(&job_key), Err(SnOsError::Sharp(SharpError::Decode { .. }))));
+
+    // only transient errors are retried
+    let n_requests = mock.n_requests();
+    mock.fail_next(MockFailure::Http(400, "bad request".to_string()));
+    let res = sharp_client.wait_for_job(&job_key, &fast_policy());
+    assert!(matches!(res, Err(SnOsError::Sharp(SharpError::Http { status: 400, .. }))));
+    assert_eq!(n_requests + 1, mock.n_requests());
+}
+
+#[rstest]
+fn sharp_client_builder() {
+    let mock = MockSharp::start().unwrap();
+    let sharp_client = SharpClient::builder()
+        .sharp_addr(&mock.url())
+        .bearer_token("secret")
+        .user_agent("snos-tests")
+        .timeout(Duration::from_secs(10))
+        .connect_timeout(Duration::from_secs(1))
+        .build()
+        .unwrap();
+
+    let job_key = sharp_client.submit_pie(SharpPie::EncodedPie("pie".to_string())).unwrap().cairo_job_key.unwrap();
+    assert_eq!(CairoJobStatus::PROCESSED, sharp_client.get_status(&job_key).unwrap().status);
+    assert_eq!(Some("Bearer secret".to_string()), mock.last_header("Authorization"));
+    assert_eq!(Some("snos-tests".to_string()), mock.last_header("User-Agent"));
+
+    let is_config_err =
+        |builder: SharpClientBuilder| matches!(builder.build(), Err(SnOsError::Sharp(SharpError::Config(_))));
+    assert!(is_config_err(SharpClient::builder().sharp_addr("not a url")));
+    assert!(is_config_err(SharpClient::builder().bearer_token("new\nline")));
+    assert!(is_config_err(SharpClient::builder().proxy("not a url")));
+    assert!(is_config_err(SharpClient::builder().client_identity_pem(b"not a cert", b"not a key")));
+    assert!(is_config_err(SharpClient::builder().root_certificate_pem(b"not a cert")));
+}
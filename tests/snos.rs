@@ -1,28 +1,44 @@
 mod common;
 
+use std::fs;
+
+use blockifier::abi::abi_utils::selector_from_name;
+use blockifier::block_context::BlockContext;
+use blockifier::execution::call_info::{CallExecution, CallInfo, Retdata};
+use blockifier::invoke_tx_args;
 use blockifier::state::state_api::State;
 use blockifier::test_utils::dict_state_reader::DictStateReader;
+use blockifier::test_utils::invoke::invoke_tx;
+use blockifier::transaction::account_transaction::AccountTransaction;
 use blockifier::transaction::objects::TransactionExecutionInfo;
+use blockifier::transaction::transaction_execution::Transaction;
 use cairo_vm::vm::errors::cairo_run_errors::CairoRunError::VmException;
 use cairo_vm::Felt252;
 use common::defs::{
-    EXPECTED_PREV_ROOT, EXPECTED_UPDATED_ROOT, TESTING_1_ADDREESS_0_12_2, TESTING_2_ADDREESS_0_12_2,
-    TESTING_BLOCK_HASH, TESTING_DELEGATE_ADDREESS_0_12_2, TESTING_HASH_0_12_2,
+    DUMMY_ACCOUNT_ADDRESS_0_12_2, EXPECTED_PREV_ROOT, EXPECTED_UPDATED_ROOT, TESTING_1_ADDREESS_0_12_2,
+    TESTING_2_ADDREESS_0_12_2, TESTING_BLOCK_HASH, TESTING_DELEGATE_ADDREESS_0_12_2, TESTING_FEE, TESTING_HASH_0_12_2,
+    TESTING_HASH_2_0_12_2,
 };
-use common::prepared_os_test::prepare_os_test;
+use common::prepared_os_test::{block_context, initial_state, prepare_os_test};
+use common::serde_utils::RawOsOutput;
 use common::{load_input, load_output};
 use rstest::rstest;
 use snos::error::SnOsError::Runner;
+use snos::error::{CommitmentInfoError, OsInputError, OutputError, SnOsError};
+use snos::execution::helper::ExecutionHelperWrapper;
+use snos::io::da::{compress, decompress, DataAvailability};
 use snos::io::input::StarknetOsInput;
-use snos::io::output::StarknetOsOutput;
+use snos::io::output::{decode_output, StarknetOsOutput};
+use snos::io::InternalTransaction;
 use snos::state::SharedState;
 use snos::utils::felt_api2vm;
-use snos::SnOsRunner;
+use snos::{OsBlock, PreparedOsRun, SnOsRunner};
 use starknet_api::block::BlockNumber;
-use starknet_api::core::PatriciaKey;
+use starknet_api::core::{Nonce, PatriciaKey};
 use starknet_api::hash::{StarkFelt, StarkHash};
 use starknet_api::state::StorageKey;
-use starknet_api::{patricia_key, stark_felt};
+use starknet_api::transaction::{Fee, Transaction as ApiTransaction, TransactionVersion};
+use starknet_api::{calldata, patricia_key, stark_felt};
 
 #[rstest]
 #[ignore]
@@ -108,6 +124,42 @@ fn parse_os_input(load_input: &StarknetOsInput) {
     assert!(!load_input.transactions.is_empty());
 }
 
+#[rstest]
+fn validate_os_input(load_input: &StarknetOsInput) {
+    load_input.validate().unwrap();
+
+    let mut bad_height = load_input.clone();
+    bad_height.contract_state_commitment_info.tree_height = 64;
+    assert!(matches!(
+        bad_height.validate(),
+        Err(OsInputError::CommitmentInfo(CommitmentInfoError::InconsistentTreeHeights(64, 251)))
+    ));
+
+    let mut unknown_class = load_input.clone();
+    unknown_class.deprecated_compiled_classes.clear();
+    assert!(matches!(unknown_class.validate(), Err(OsInputError::UnknownClassHash(_, _))));
+}
+
+#[rstest]
+fn typed_os_transactions(load_input: &StarknetOsInput) {
+    let deploy = load_input.transactions.iter().find(|tx| matches!(tx, InternalTransaction::Deploy(_))).unwrap();
+    let raw_deploy = serde_json::to_value(deploy).unwrap();
+    assert_eq!(raw_deploy["type"], "DEPLOY");
+
+    let round_trip: InternalTransaction = serde_json::from_value(raw_deploy.clone()).unwrap();
+    assert_eq!(round_trip.hash_value(), deploy.hash_value());
+    assert_eq!(round_trip.tx_type(), "DEPLOY");
+
+    let mut incomplete_deploy = raw_deploy.clone();
+    incomplete_deploy.as_object_mut().unwrap().remove("contract_address_salt");
+    let err = serde_json::from_value::<InternalTransaction>(incomplete_deploy).unwrap_err();
+    assert!(err.to_string().contains("contract_address_salt"));
+
+    let mut unknown_type = raw_deploy;
+    unknown_type["type"] = "DEPLOY_CONTRACT".into();
+    assert!(serde_json::from_value::<InternalTransaction>(unknown_type).is_err());
+}
+
 #[rstest]
 fn parse_os_output(load_input: &StarknetOsInput, load_output: StarknetOsOutput) {
     assert_eq!(load_input.contract_state_commitment_info.previous_root, load_output.prev_state_root);
@@ -115,8 +167,422 @@ fn parse_os_output(load_input: &StarknetOsInput, load_output: StarknetOsOutput)
     assert_eq!(Felt252::from(1), load_output.block_number);
     assert_eq!(load_input.block_hash, load_output.block_hash);
     assert_eq!(felt_api2vm(load_input.general_config.starknet_os_config.hash()), load_output.config_hash);
-    assert_eq!(4, load_output.messages_to_l1.len());
-    assert_eq!(4, load_output.messages_to_l2.len());
-    assert_eq!(4, load_output.state_updates.len());
-    assert_eq!(4, load_output.contract_class_diff.len());
+    assert_eq!(5, load_output.messages_to_l1.len());
+    assert_eq!(6, load_output.messages_to_l2.len());
+    assert_eq!(44, load_output.state_updates.len());
+    assert_eq!(0, load_output.contract_class_diff.len());
+}
+
+#[rstest]
+fn encode_os_output(load_output: StarknetOsOutput) {
+    let raw_output = fs::read_to_string("tests/common/data/os_output.json").unwrap();
+    let raw_output: RawOsOutput = serde_json::from_str(&raw_output).unwrap();
+    assert_eq!(raw_output.0, load_output.encode().unwrap());
+
+    let json_output = serde_json::to_string(&load_output).unwrap();
+    assert!(json_output.contains(&load_output.block_hash.to_hex_string()));
+    let deserialized_output: StarknetOsOutput = serde_json::from_str(&json_output).unwrap();
+    assert_eq!(load_output, deserialized_output);
+}
+
+#[rstest]
+fn decode_malformed_os_output(load_output: StarknetOsOutput) {
+    let raw_output = load_output.encode().unwrap();
+
+    let truncated_message = raw_output[..8].to_vec();
+    assert!(matches!(decode_output(truncated_message), Err(SnOsError::Output(OutputError::SegmentOutOfBounds(6, 5)))));
+
+    let truncated_state_diff = raw_output[..raw_output.len() - 2].to_vec();
+    assert!(matches!(decode_output(truncated_state_diff), Err(SnOsError::Output(OutputError::UnexpectedEnd(62)))));
+
+    let header_only = raw_output[..5].to_vec();
+    assert!(matches!(decode_output(header_only), Err(SnOsError::Output(OutputError::UnexpectedEnd(5)))));
+
+    let mut bad_length = raw_output.clone();
+    bad_length[5] = Felt252::MAX;
+    assert!(matches!(decode_output(bad_length), Err(SnOsError::Output(OutputError::InvalidLength(5, _)))));
+
+    let mut trailing = raw_output.clone();
+    trailing.push(Felt252::ONE);
+    assert!(matches!(
+        decode_output(trailing),
+        Err(SnOsError::Output(OutputError::TrailingData(offset, 1))) if offset == raw_output.len()
+    ));
+
+    // segments are not limited to u16 lengths
+    let mut large_output = load_output.clone();
+    large_output.messages_to_l1 = vec![Felt252::ONE, Felt252::TWO, Felt252::from(70_000)];
+    large_output.messages_to_l1.extend(vec![Felt252::ZERO; 70_000]);
+    let decoded_output = decode_output(large_output.encode().unwrap()).unwrap();
+    assert_eq!(70_000, decoded_output.decode_messages_to_l1().unwrap()[0].payload.len());
+}
+
+#[rstest]
+fn data_availability_round_trip(load_output: StarknetOsOutput) {
+    let da = DataAvailability::from_output(&load_output).unwrap();
+    assert_eq!(5, da.contract_updates.len());
+
+    let encoded = da.encode().unwrap();
+    let mut expected = vec![Felt252::from(5)];
+    expected.extend(&load_output.state_updates);
+    expected.push(Felt252::ZERO);
+    assert_eq!(expected, encoded);
+    assert_eq!(da, DataAvailability::decode(&encoded).unwrap());
+
+    let mut trailing = encoded;
+    trailing.push(Felt252::ONE);
+    assert!(matches!(DataAvailability::decode(&trailing), Err(SnOsError::Output(OutputError::TrailingData(_, 1)))));
+}
+
+#[rstest]
+fn data_availability_compression_round_trip(load_output: StarknetOsOutput) {
+    let da = DataAvailability::from_output(&load_output).unwrap();
+    let compressed = da.compress().unwrap();
+    assert!(compressed.len() < da.encode().unwrap().len());
+    assert_eq!(da, DataAvailability::decompress(&compressed).unwrap());
+
+    assert_eq!(Vec::<Felt252>::new(), decompress(&compress(&[])).unwrap());
+}
+
+#[rstest]
+// header (version, data length, the 6 unique value buckets and the repeating values bucket, 20
+// bits each), the 15 bit bucket, the pointer to the first `5` and the bucket of each element
+#[case::repeated_value(
+    vec![Felt252::from(5), Felt252::from(5)],
+    vec![
+        Felt252::from_hex("0x10000100000000000000000000000000000200000").unwrap(),
+        Felt252::from(5),
+        Felt252::ZERO,
+        Felt252::from(5 + 6 * 7),
+    ],
+)]
+// 2**200 goes to the 252 bit bucket, 2**100 to the 125 bit bucket and 1 and 7 to the 15 bit bucket,
+// packed 1 + 7 * 2**15
+#[case::mixed_buckets(
+    vec![
+        Felt252::ONE,
+        Felt252::from(1u128 << 100),
+        Felt252::from(1u128 << 100) * Felt252::from(1u128 << 100),
+        Felt252::ONE,
+        Felt252::from(1u128 << 100),
+        Felt252::from(7),
+    ],
+    vec![
+        Felt252::from_hex("0x20000200000000000000000001000010000600000").unwrap(),
+        Felt252::from(1u128 << 100) * Felt252::from(1u128 << 100),
+        Felt252::from(1u128 << 100),
+        Felt252::from(0x38001),
+        Felt252::from(2 + 4),
+        Felt252::from(5 + 7 + 6 * 343 + 6 * 2401 + 5 * 16807),
+    ],
+)]
+fn state_diff_compression(#[case] data: Vec<Felt252>, #[case] expected: Vec<Felt252>) {
+    assert_eq!(expected, compress(&data));
+    assert_eq!(data, decompress(&expected).unwrap());
+}
+
+#[rstest]
+fn state_diff_decompression_errors() {
+    let compressed = compress(&[Felt252::from(5), Felt252::from(5)]);
+
+    let mut bad_version = compressed.clone();
+    bad_version[0] = bad_version[0] + Felt252::ONE;
+    assert!(matches!(decompress(&bad_version), Err(OutputError::UnsupportedCompressionVersion(1))));
+
+    // the repeating value points past the single unique value
+    let mut bad_pointer = compressed.clone();
+    bad_pointer[2] = Felt252::ONE;
+    assert!(matches!(decompress(&bad_pointer), Err(OutputError::MissingCompressedValue(1))));
+
+    // both elements read from the 15 bit bucket holding a single value
+    let mut bad_bucket = compressed.clone();
+    bad_bucket[3] = Felt252::from(5 + 5 * 7);
+    assert!(matches!(decompress(&bad_bucket), Err(OutputError::MissingCompressedValue(1))));
+
+    assert!(matches!(decompress(&compressed[..3]), Err(OutputError::SegmentOutOfBounds(3, 1))));
+}
+
+#[rstest]
+fn os_output_message_hashes(load_output: StarknetOsOutput) {
+    let message_to_l1 = &load_output.decode_messages_to_l1().unwrap()[0];
+    assert_eq!("950f3cf6aa5d8f7ddd2aff1e75643205b708941d063cf590d4f421dc680448e1", hex::encode(message_to_l1.hash()));
+
+    let message_to_l2 = &load_output.decode_messages_to_l2().unwrap()[0];
+    assert_eq!("c7a9b58414ac8533a72a7589a1ffe977cd6988b13766a7e76d264e6f995ade52", hex::encode(message_to_l2.hash()));
+}
+
+#[rstest]
+fn decode_os_output_fields(load_output: StarknetOsOutput) {
+    let messages_to_l1 = load_output.decode_messages_to_l1().unwrap();
+    assert_eq!(1, messages_to_l1.len());
+    assert_eq!(Felt252::from(0x55), messages_to_l1[0].to);
+    assert_eq!(vec![Felt252::from(12), Felt252::from(34)], messages_to_l1[0].payload);
+
+    let messages_to_l2 = load_output.decode_messages_to_l2().unwrap();
+    assert_eq!(1, messages_to_l2.len());
+    assert_eq!(Felt252::from(0x55), messages_to_l2[0].from);
+    assert_eq!(Felt252::ZERO, messages_to_l2[0].nonce);
+    assert_eq!(vec![Felt252::from(2)], messages_to_l2[0].payload);
+
+    let contract_updates = load_output.decode_contract_updates().unwrap();
+    assert_eq!(5, contract_updates.len());
+    assert_eq!(6, contract_updates[0].storage_updates.len());
+    assert!(contract_updates[0].class_hash.is_some());
+    assert_eq!(Felt252::from(24), contract_updates[3].nonce);
+    assert_eq!(None, contract_updates[3].class_hash);
+    assert!(contract_updates[4].storage_updates.is_empty());
+
+    assert!(load_output.decode_class_declarations().unwrap().is_empty());
+}
+
+#[rstest]
+fn snos_runner_builder() {
+    let runner = SnOsRunner::builder().layout("all_cairo").os_path("build/os_latest.json").max_steps(1_000_000).build();
+    assert!(runner.is_ok());
+
+    let bad_layout = SnOsRunner::builder().layout("small").build();
+    assert!(matches!(bad_layout, Err(SnOsError::RunnerConfig(_))));
+
+    let bad_os_path = SnOsRunner::builder().os_path("build/does_not_exist.json").build();
+    assert!(matches!(bad_os_path, Err(SnOsError::RunnerConfig(_))));
+
+    let bad_max_steps = SnOsRunner::builder().max_steps(0).build();
+    assert!(matches!(bad_max_steps, Err(SnOsError::RunnerConfig(_))));
+
+    assert!(SnOsRunner::builder().proof_mode(false).build().is_ok());
+    let proof_mode = SnOsRunner::builder().proof_mode(true).build();
+    assert!(matches!(proof_mode, Err(SnOsError::RunnerConfig(_))));
+}
+
+#[rstest]
+fn snos_runner_program_hash() {
+    let hash = SnOsRunner::default().program_hash().unwrap();
+    assert_eq!(hash, SnOsRunner::with_os_path("build/os_latest.json").program_hash().unwrap());
+    assert_ne!(hash, SnOsRunner::with_os_path("build/programs/fact.json").program_hash().unwrap());
+
+    assert!(SnOsRunner::with_os_path("build/does_not_exist.json").program_hash().is_err());
+}
+
+#[rstest]
+fn run_blocks_rejects_out_of_sequence_block(mut initial_state: SharedState<DictStateReader>) {
+    let mut block_context = initial_state.block_context.clone();
+    block_context.block_number = BlockNumber(initial_state.get_block_num().0 + 5);
+
+    let block = OsBlock { block_context, block_hash: Felt252::ZERO, transactions: vec![], os_transactions: vec![] };
+    let res = SnOsRunner::default().run_blocks(&mut initial_state, vec![block]);
+
+    assert!(matches!(res, Err(SnOsError::BlockSequence(_))));
+}
+
+#[rstest]
+fn commit_blocks(prepare_os_test: (SharedState<DictStateReader>, Vec<TransactionExecutionInfo>)) {
+    let (mut shared_state, _) = prepare_os_test;
+    let account = felt_api2vm(*DUMMY_ACCOUNT_ADDRESS_0_12_2.0.key());
+    let testing_1 = felt_api2vm(*TESTING_1_ADDREESS_0_12_2.0.key());
+
+    let first = shared_state.commit_block(vec![], Felt252::ONE).unwrap();
+    assert_eq!(Felt252::from_hex(EXPECTED_PREV_ROOT).unwrap(), first.contract_state_commitment_info.previous_root);
+    // contracts are described as of the start of the block
+    assert_eq!(Felt252::ONE, first.contracts[&account].nonce);
+    assert_eq!(Felt252::ZERO, first.contracts[&testing_1].contract_hash);
+    assert!(first.deprecated_compiled_classes.contains_key(&Felt252::from_hex(TESTING_HASH_2_0_12_2).unwrap()));
+    assert!(first.compiled_classes.is_empty());
+
+    // the second block only holds its own write
+    assert!(shared_state.block_diff().storage_updates.is_empty());
+    let key = StorageKey(patricia_key!(85_u32));
+    let _ = shared_state.cache.set_storage_at(*TESTING_1_ADDREESS_0_12_2, key, stark_felt!(1_u32));
+    let diff = shared_state.block_diff();
+    assert_eq!(1, diff.storage_updates.len());
+    assert_eq!(Some(&stark_felt!(1_u32)), diff.storage_updates[&*TESTING_1_ADDREESS_0_12_2].get(&key));
+    assert_eq!(1, diff.storage_updates[&*TESTING_1_ADDREESS_0_12_2].len());
+    assert!(diff.address_to_nonce.is_empty());
+    assert!(diff.address_to_class_hash.is_empty());
+
+    let second = shared_state.commit_block(vec![], Felt252::TWO).unwrap();
+    assert_eq!(BlockNumber(3), shared_state.get_block_num());
+    assert_eq!(first.contract_state_commitment_info.updated_root, second.contract_state_commitment_info.previous_root);
+    assert_ne!(first.contract_state_commitment_info.updated_root, second.contract_state_commitment_info.updated_root);
+    assert_eq!(vec![&testing_1], second.contracts.keys().collect::<Vec<_>>());
+    assert_eq!(Felt252::from_hex(TESTING_HASH_2_0_12_2).unwrap(), second.contracts[&testing_1].contract_hash);
+    assert_ne!(Felt252::ZERO, second.contracts[&testing_1].storage_commitment_tree.root);
+    assert!(second.deprecated_compiled_classes.contains_key(&Felt252::from_hex(TESTING_HASH_2_0_12_2).unwrap()));
+    assert!(shared_state.block_diff().storage_updates.is_empty());
+
+    // the commitment facts link the roots of both blocks
+    first.validate().unwrap();
+    second.validate().unwrap();
+}
+
+#[rstest]
+fn run_blocks_reaches_vm(prepare_os_test: (SharedState<DictStateReader>, Vec<TransactionExecutionInfo>)) {
+    let (mut shared_state, _) = prepare_os_test;
+    let blocks = (0..2)
+        .map(|i| {
+            let mut block_context = shared_state.block_context.clone();
+            block_context.block_number = BlockNumber(shared_state.get_block_num().0 + i);
+            OsBlock { block_context, block_hash: Felt252::from(i), transactions: vec![], os_transactions: vec![] }
+        })
+        .collect();
+
+    // the committed input passes validation and the run stops on the step limit
+    let runner = SnOsRunner::builder().max_steps(1_000).build().unwrap();
+    let res = runner.run_blocks(&mut shared_state, blocks);
+    assert!(matches!(res, Err(SnOsError::Runner(_))));
+}
+
+/// Block calling `set_value(85, value)` on the second testing contract from the dummy account.
+fn set_value_block(shared_state: &SharedState<DictStateReader>, block_number: u64, nonce: u64, value: u32) -> OsBlock {
+    let mut block_context = shared_state.block_context.clone();
+    block_context.block_number = BlockNumber(block_number);
+
+    let tx = invoke_tx(invoke_tx_args! {
+        max_fee: Fee(TESTING_FEE),
+        nonce: Nonce(stark_felt!(nonce)),
+        sender_address: *DUMMY_ACCOUNT_ADDRESS_0_12_2,
+        calldata: calldata![
+            *TESTING_2_ADDREESS_0_12_2.0.key(),
+            selector_from_name("set_value").0,
+            stark_felt!(2_u8),
+            stark_felt!(85_u8),
+            stark_felt!(value)
+        ],
+        version: TransactionVersion::ONE,
+    });
+    let os_tx = InternalTransaction::from_api(ApiTransaction::Invoke(tx.tx.clone()), tx.tx_hash).unwrap();
+
+    OsBlock {
+        block_context,
+        block_hash: Felt252::from(block_number),
+        transactions: vec![Transaction::AccountTransaction(AccountTransaction::Invoke(tx))],
+        os_transactions: vec![os_tx],
+    }
+}
+
+#[rstest]
+fn run_blocks_proves_consecutive_blocks(
+    prepare_os_test: (SharedState<DictStateReader>, Vec<TransactionExecutionInfo>),
+) {
+    let (mut shared_state, _) = prepare_os_test;
+    // commit the fixture transactions, the proven blocks start from their state
+    shared_state.commit_block(vec![], Felt252::ONE).unwrap();
+
+    let blocks = vec![set_value_block(&shared_state, 2, 24, 1000), set_value_block(&shared_state, 3, 25, 2000)];
+    let proven_blocks = SnOsRunner::default().run_blocks(&mut shared_state, blocks).unwrap();
+    assert_eq!(2, proven_blocks.len());
+
+    let (first, second) = (&proven_blocks[0], &proven_blocks[1]);
+    assert_eq!(first.os_output, StarknetOsOutput::from_pie(&first.pie).unwrap());
+    assert_eq!(first.os_output.new_state_root, second.os_output.prev_state_root);
+    assert_ne!(first.os_output.prev_state_root, first.os_output.new_state_root);
+    assert_ne!(second.os_output.prev_state_root, second.os_output.new_state_root);
+    assert_eq!(Felt252::TWO, first.os_output.block_number);
+    assert_eq!(Felt252::THREE, second.os_output.block_number);
+
+    // each block writes its own value
+    let testing_2 = felt_api2vm(*TESTING_2_ADDREESS_0_12_2.0.key());
+    for (block, value) in [(first, 1000), (second, 2000)] {
+        let contract_updates = block.os_output.decode_contract_updates().unwrap();
+        let update = contract_updates.iter().find(|update| update.address == testing_2).unwrap();
+        assert_eq!(vec![(Felt252::from(85), Felt252::from(value))], update.storage_updates);
+    }
+    assert_eq!(
+        second.os_input.contract_state_commitment_info.previous_root,
+        first.os_input.contract_state_commitment_info.updated_root
+    );
+}
+
+#[rstest]
+fn snos_runner_is_thread_safe() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<SnOsRunner>();
+
+    let pies = SnOsRunner::default().run_parallel(vec![], 4).unwrap();
+    assert!(pies.is_empty());
+}
+
+#[rstest]
+#[ignore]
+fn run_parallel_returns_pies_in_input_order(
+    load_input: &StarknetOsInput,
+    prepare_os_test: (SharedState<DictStateReader>, Vec<TransactionExecutionInfo>),
+) {
+    let (shared_state, execution_infos) = prepare_os_test;
+    assert!(!load_input.transactions.is_empty());
+
+    // the block hash is written to the OS output, telling the runs apart
+    let block_hashes: Vec<Felt252> = (1..=3).map(Felt252::from).collect();
+    let runs = block_hashes
+        .iter()
+        .map(|block_hash| PreparedOsRun {
+            os_input: StarknetOsInput { block_hash: *block_hash, ..load_input.clone() },
+            block_context: shared_state.block_context.clone(),
+            execution_infos: execution_infos.clone(),
+        })
+        .collect();
+
+    let pies = SnOsRunner::default().run_parallel(runs, 2).unwrap();
+    assert_eq!(3, pies.len());
+    for (pie, block_hash) in pies.iter().zip(block_hashes) {
+        assert_eq!(block_hash, StarknetOsOutput::from_pie(pie).unwrap().block_hash);
+    }
+}
+
+#[rstest]
+fn execution_helper_handles_reverted_tx(block_context: BlockContext) {
+    // blockifier keeps no execute call info for a reverted transaction
+    let tx_execution_info = TransactionExecutionInfo {
+        validate_call_info: Some(CallInfo::default()),
+        execute_call_info: None,
+        fee_transfer_call_info: Some(CallInfo::default()),
+        actual_fee: Fee(42),
+        revert_error: Some("reverted".to_string()),
+        ..Default::default()
+    };
+
+    let mut execution_helper = ExecutionHelperWrapper::new(vec![tx_execution_info], &block_context);
+    assert_eq!(execution_helper.actual_fee(), None);
+    assert_eq!(execution_helper.is_reverted(), None);
+
+    execution_helper.start_tx(None);
+    assert_eq!(execution_helper.actual_fee(), Some(Felt252::from(42)));
+    assert_eq!(execution_helper.is_reverted(), Some(true));
+
+    // validate and fee transfer
+    execution_helper.skip_call();
+    execution_helper.skip_call();
+    execution_helper.end_tx();
+}
+
+#[rstest]
+fn execution_helper_runs_inner_calls_of_failed_calls(block_context: BlockContext) {
+    let failed_call = CallInfo {
+        execution: CallExecution { failed: true, retdata: Retdata(vec![stark_felt!("0x1")]), ..Default::default() },
+        inner_calls: vec![CallInfo::default()],
+        ..Default::default()
+    };
+    let tx_execution_info = TransactionExecutionInfo {
+        execute_call_info: Some(CallInfo { inner_calls: vec![failed_call], ..Default::default() }),
+        ..Default::default()
+    };
+
+    let mut execution_helper = ExecutionHelperWrapper::new(vec![tx_execution_info], &block_context);
+    execution_helper.start_tx(None);
+    assert_eq!(execution_helper.execution_helper.borrow().call_iter.len(), 3);
+
+    // the caller sees the failure of its inner call
+    execution_helper.enter_call(None);
+    let result = execution_helper.execution_helper.borrow_mut().result_iter.next().unwrap();
+    assert!(result.failed);
+    assert_eq!(result.retdata.0, vec![stark_felt!("0x1")]);
+    execution_helper.exit_call();
+
+    // the failed call is run until it fails, consuming the result of its own inner call
+    execution_helper.enter_call(None);
+    let result = execution_helper.execution_helper.borrow_mut().result_iter.next().unwrap();
+    assert!(!result.failed);
+    execution_helper.exit_call();
+
+    execution_helper.skip_call();
+    execution_helper.end_tx();
 }